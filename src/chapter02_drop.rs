@@ -3,35 +3,88 @@
 //! Demonstrates practical uses of the Drop trait for automatic cleanup,
 //! timing, and scope-based actions.
 
-use std::time::Instant;
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 // ============================================================================
 // Timer: Automatic timing of code blocks
 // ============================================================================
 
-/// A timer that prints elapsed time when it goes out of scope.
+thread_local! {
+    /// Number of timers started on this thread that are still alive.
+    ///
+    /// Each timer keeps a handle to this counter, so a timer sent to
+    /// another thread still decrements the count it incremented.
+    static TIMER_DEPTH: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+}
+
+fn timer_depth() -> usize {
+    TIMER_DEPTH.with(|depth| depth.load(Ordering::Relaxed))
+}
+
+/// Receives a timer's name and elapsed time when it is dropped.
+type TimerSink = Box<dyn Fn(&str, Duration) + Send>;
+
+/// A timer that reports elapsed time when it goes out of scope.
 ///
 /// Creating a Timer starts the clock; dropping it stops the clock
-/// and prints the elapsed duration.
+/// and hands the elapsed duration to its sink, which prints it by
+/// default. Nested timers are indented by how many timers were
+/// already alive when they started.
 struct Timer {
     name: String,
     start: Instant,
+    depth: usize,
+    depth_counter: Arc<AtomicUsize>,
+    sink: TimerSink,
+    slow_threshold: Option<Duration>,
 }
 
 impl Timer {
     fn new(name: &str) -> Self {
-        println!("[Timer '{}'] Started", name);
+        let indent = "  ".repeat(timer_depth());
+        println!("{}[Timer '{}'] Started", indent, name);
+        Timer::new_with_sink(name, move |name, elapsed| {
+            println!("{}[Timer '{}'] Elapsed: {:?}", indent, name, elapsed);
+        })
+    }
+
+    /// A timer that calls `sink` on drop instead of printing.
+    ///
+    /// Drop runs on early returns and during unwinding, so the sink
+    /// sees every exit path.
+    fn new_with_sink(name: &str, sink: impl Fn(&str, Duration) + Send + 'static) -> Self {
+        let depth_counter = TIMER_DEPTH.with(Arc::clone);
+        let depth = depth_counter.fetch_add(1, Ordering::Relaxed);
         Timer {
             name: name.to_string(),
             start: Instant::now(),
+            depth,
+            depth_counter,
+            sink: Box::new(sink),
+            slow_threshold: None,
         }
     }
+
+    /// Also print a SLOW line on drop if the elapsed time exceeds `threshold`.
+    fn warn_if_over(mut self, threshold: Duration) -> Self {
+        self.slow_threshold = Some(threshold);
+        self
+    }
 }
 
 impl Drop for Timer {
     fn drop(&mut self) {
         let elapsed = self.start.elapsed();
-        println!("[Timer '{}'] Elapsed: {:?}", self.name, elapsed);
+        (self.sink)(&self.name, elapsed);
+        if let Some(threshold) = self.slow_threshold.filter(|&t| elapsed > t) {
+            println!("{}[Timer '{}'] SLOW: {:?} exceeded {:?}",
+                     "  ".repeat(self.depth), self.name, elapsed, threshold);
+        }
+        self.depth_counter.fetch_sub(1, Ordering::Relaxed);
     }
 }
 
@@ -73,37 +126,265 @@ fn demo_timer() {
 
     let result2 = process_with_early_return(false);
     println!("Result (full): {}\n", result2);
+
+    // Called under an outer timer, the function's timer nests beneath it
+    {
+        let _request = Timer::new("handle_request");
+        process_with_early_return(true);
+        process_with_early_return(false);
+    }
+    println!();
+
+    // Nested timers produce an indented hierarchy
+    {
+        let _outer = Timer::new("outer");
+        {
+            let _inner = Timer::new("inner");
+            let _innermost = Timer::new("innermost");
+            do_some_work(100_000);
+        }
+        let _sibling = Timer::new("sibling");
+        do_some_work(100_000);
+    }
+
+    // A threshold flags unexpectedly slow blocks
+    println!();
+    for (label, iterations) in [("fast_path", 1_000), ("hot_path", 5_000_000)] {
+        let _timer = Timer::new(label).warn_if_over(Duration::from_millis(1));
+        do_some_work(iterations);
+    }
+
+    // A sink collects timings instead of printing them
+    let timings = Arc::new(Mutex::new(Vec::new()));
+    let collecting = |name: &str| {
+        let timings = Arc::clone(&timings);
+        Timer::new_with_sink(name, move |_, elapsed| timings.lock().unwrap().push(elapsed))
+    };
+
+    fn collected_with_early_return(timer: Timer, should_return_early: bool) -> u64 {
+        let _timer = timer;
+        if should_return_early {
+            return 0;
+        }
+        do_some_work(500_000)
+    }
+
+    collected_with_early_return(collecting("early"), true);
+    collected_with_early_return(collecting("full"), false);
+
+    let timer = collecting("panicking");
+    let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
+        let _timer = timer;
+        panic!("work failed");
+    }));
+    println!("\nPanicking block caught: {}", outcome.is_err());
+
+    let timings = timings.lock().unwrap();
+    println!("Collected {} timings (early return, full run, panic): {:?}", timings.len(), timings);
+
+    // Sinks can also capture the nesting level each timer would indent to
+    let levels = Arc::new(Mutex::new(Vec::new()));
+    let tracing = |name: &str| {
+        let level = timer_depth();
+        levels.lock().unwrap().push(level);
+        let levels = Arc::clone(&levels);
+        Timer::new_with_sink(name, move |_, _| levels.lock().unwrap().push(level))
+    };
+    {
+        let _outer = tracing("outer");
+        let _inner = tracing("inner");
+        let _innermost = tracing("innermost");
+    }
+    let nested = std::mem::take(&mut *levels.lock().unwrap());
+    println!("Nesting levels at start/stop: {:?}", nested);
+    assert_eq!(nested, [0, 1, 2, 2, 1, 0]);
+
+    // Dropping out of creation order still leaves the depth consistent
+    let first = tracing("first");
+    let second = tracing("second");
+    drop(first);
+    let third = tracing("third"); // Only `second` is alive, so this nests one level
+    drop(second);
+    drop(third);
+    drop(tracing("after")); // Back at the top level
+    let out_of_order = levels.lock().unwrap();
+    println!("Out-of-order levels at start/stop: {:?}", out_of_order);
+    assert_eq!(*out_of_order, [0, 1, 0, 1, 1, 1, 0, 0]);
+}
+
+// ============================================================================
+// BudgetGuard: React only when a block runs over its time budget
+// ============================================================================
+
+/// A guard that checks elapsed time against a budget when dropped.
+///
+/// Unlike Timer, it stays silent on the happy path: the callback only
+/// runs, with the actual elapsed time, if the block overran.
+struct BudgetGuard<F: FnOnce(Duration)> {
+    budget: Duration,
+    start: Instant,
+    on_overrun: Option<F>,
+}
+
+impl<F: FnOnce(Duration)> BudgetGuard<F> {
+    fn new(budget: Duration, on_overrun: F) -> Self {
+        BudgetGuard {
+            budget,
+            start: Instant::now(),
+            on_overrun: Some(on_overrun),
+        }
+    }
+
+    /// Accept the elapsed time as-is and skip the budget check.
+    fn commit(mut self) {
+        self.on_overrun = None;
+    }
+}
+
+impl<F: FnOnce(Duration)> Drop for BudgetGuard<F> {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        if elapsed > self.budget {
+            if let Some(on_overrun) = self.on_overrun.take() {
+                on_overrun(elapsed);
+            }
+        }
+    }
+}
+
+fn demo_budget_guard() {
+    println!("\n=== BudgetGuard Demo ===\n");
+
+    let overruns = Cell::new(0);
+    let report = |label: &'static str| {
+        let overruns = &overruns;
+        move |elapsed: Duration| {
+            overruns.set(overruns.get() + 1);
+            println!("  [Budget] '{}' overran: took {:?}", label, elapsed);
+        }
+    };
+
+    {
+        let _guard = BudgetGuard::new(Duration::from_millis(100), report("fast_path"));
+        do_some_work(10_000);
+    }
+    println!("After fast_path, overruns: {}", overruns.get());
+
+    {
+        let _guard = BudgetGuard::new(Duration::from_millis(10), report("slow_path"));
+        thread::sleep(Duration::from_millis(30));
+    }
+    println!("After slow_path, overruns: {}", overruns.get());
+
+    // A known-slow batch job: commit() opts out of the check
+    let guard = BudgetGuard::new(Duration::from_millis(10), report("batch_job"));
+    thread::sleep(Duration::from_millis(30));
+    guard.commit();
+    println!("After committed batch_job, overruns: {}", overruns.get());
 }
 
 // ============================================================================
 // ScopeGuard: Run arbitrary cleanup code when scope ends
 // ============================================================================
 
+/// Which exits from a scope trigger a ScopeGuard's action.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RunOn {
+    Always,
+    Unwind,
+    Success,
+}
+
 /// A guard that runs a closure when dropped.
 ///
 /// Useful for ensuring cleanup happens regardless of how
 /// a scope is exited (normal return, early return, or panic).
 struct ScopeGuard<F: FnOnce()> {
     action: Option<F>,
+    run_on: RunOn,
 }
 
 impl<F: FnOnce()> ScopeGuard<F> {
     fn new(action: F) -> Self {
         ScopeGuard {
             action: Some(action),
+            run_on: RunOn::Always,
+        }
+    }
+
+    /// Runs only if the scope is left by a panic, e.g. to roll back.
+    fn on_unwind(action: F) -> Self {
+        ScopeGuard {
+            action: Some(action),
+            run_on: RunOn::Unwind,
+        }
+    }
+
+    /// Runs only if the scope is left normally, e.g. to commit.
+    fn on_success(action: F) -> Self {
+        ScopeGuard {
+            action: Some(action),
+            run_on: RunOn::Success,
         }
     }
 
     /// Disarm the guard, preventing the action from running.
-    #[allow(dead_code)]
     fn disarm(&mut self) {
         self.action = None;
     }
+
+    /// Install a new action, e.g. after `disarm` once the right cleanup is known.
+    fn rearm(&mut self, action: F) {
+        self.action = Some(action);
+    }
+
+    /// Swap in a new action, handing back the old one if it was still armed.
+    fn replace(&mut self, action: F) -> Option<F> {
+        self.action.replace(action)
+    }
 }
 
 impl<F: FnOnce()> Drop for ScopeGuard<F> {
     fn drop(&mut self) {
-        if let Some(action) = self.action.take() {
+        let should_run = match self.run_on {
+            RunOn::Always => true,
+            RunOn::Unwind => thread::panicking(),
+            RunOn::Success => !thread::panicking(),
+        };
+        if let Some(action) = self.action.take().filter(|_| should_run) {
+            action();
+        }
+    }
+}
+
+/// A single guard for several cleanups, run in reverse order on drop.
+///
+/// Mirrors how separate guards would unwind: the last resource set up
+/// is the first one torn down.
+struct GuardStack {
+    actions: Vec<Box<dyn FnOnce()>>,
+}
+
+impl GuardStack {
+    fn new() -> Self {
+        GuardStack {
+            actions: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, action: impl FnOnce() + 'static) {
+        self.actions.push(Box::new(action));
+    }
+
+    /// Disarm every registered cleanup, e.g. once an operation has committed.
+    fn disarm_all(&mut self) {
+        self.actions.clear();
+    }
+}
+
+impl Drop for GuardStack {
+    fn drop(&mut self) {
+        while let Some(action) = self.actions.pop() {
             action();
         }
     }
@@ -165,6 +446,88 @@ fn demo_scope_guard() {
     println!("Calling guarded_operation(true):");
     let _ = guarded_operation(true);
     println!("Logging after success op: {}\n", is_logging_enabled());
+
+    // One GuardStack instead of three separate guards
+    println!("Setting up three resources:");
+    {
+        let mut guards = GuardStack::new();
+        for name in ["database", "cache", "socket"] {
+            println!("  Opened {}", name);
+            guards.push(move || println!("  Closed {}", name));
+        }
+        println!("  Scope ending, cleanups run in reverse:");
+    }
+
+    // Once the work has committed, the cleanups are no longer needed
+    println!("Setting up a committed operation:");
+    {
+        let mut guards = GuardStack::new();
+        guards.push(|| println!("  Deleted temp file"));
+        guards.push(|| println!("  Rolled back transaction"));
+        println!("  Committed, disarming cleanups");
+        guards.disarm_all();
+    }
+    println!("  Nothing was undone");
+
+    // Panic-aware guards: rollback only on failure, commit only on success
+    fn transfer(fail: bool, log: &RefCell<Vec<&'static str>>) {
+        let _rollback = ScopeGuard::on_unwind(|| log.borrow_mut().push("rollback"));
+        let _commit = ScopeGuard::on_success(|| log.borrow_mut().push("commit"));
+        let _always = ScopeGuard::new(|| log.borrow_mut().push("release lock"));
+        if fail {
+            panic!("transfer failed");
+        }
+    }
+
+    for fail in [false, true] {
+        let log = RefCell::new(Vec::new());
+        let panicked = quietly_catch_panic(|| transfer(fail, &log));
+        println!("\ntransfer(fail={}) panicked: {}, guards ran: {:?}",
+                 fail, panicked, log.borrow());
+    }
+
+    demo_rearm_guard();
+}
+
+/// Guards whose cleanup is decided partway through the scope.
+fn demo_rearm_guard() {
+    // Boxed so that different closures share one `F`
+    type Action<'a> = Box<dyn FnOnce() + 'a>;
+
+    fn record<'a>(log: &'a RefCell<Vec<&'static str>>, event: &'static str) -> Action<'a> {
+        Box::new(move || log.borrow_mut().push(event))
+    }
+    let log = RefCell::new(Vec::new());
+
+    {
+        let mut guard = ScopeGuard::new(record(&log, "delete temp file"));
+        // The temp file was renamed into place, so deleting it is wrong now
+        guard.disarm();
+        guard.rearm(record(&log, "notify watchers"));
+    }
+    println!("\ndisarm -> rearm -> drop ran: {:?}", log.take());
+
+    {
+        let mut guard = ScopeGuard::new(record(&log, "delete temp file"));
+        guard.disarm();
+    }
+    println!("disarm -> drop ran: {:?}", log.take());
+
+    {
+        let mut guard = ScopeGuard::new(record(&log, "release v1 lock"));
+        let previous = guard.replace(record(&log, "release v2 lock"));
+        println!("replace returned the old action: {}", previous.is_some());
+    }
+    println!("replace -> drop ran: {:?}", log.take());
+}
+
+/// Runs `f`, reporting whether it panicked, without printing the panic message.
+fn quietly_catch_panic(f: impl FnOnce()) -> bool {
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+    std::panic::set_hook(previous_hook);
+    result.is_err()
 }
 
 // ============================================================================
@@ -176,18 +539,27 @@ struct Announcer {
     name: String,
 }
 
+/// Announcers currently alive, across all threads.
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
 impl Announcer {
     fn new(name: &str) -> Self {
         println!("  Created: {}", name);
+        LIVE.fetch_add(1, Ordering::SeqCst);
         Announcer {
             name: name.to_string(),
         }
     }
+
+    fn live_count() -> usize {
+        LIVE.load(Ordering::SeqCst)
+    }
 }
 
 impl Drop for Announcer {
     fn drop(&mut self) {
         println!("  Dropped: {}", self.name);
+        LIVE.fetch_sub(1, Ordering::SeqCst);
     }
 }
 
@@ -199,8 +571,10 @@ fn demo_drop_ordering() {
         let _first = Announcer::new("first");
         let _second = Announcer::new("second");
         let _third = Announcer::new("third");
-        println!("\nScope ending, drops occur in reverse order:");
+        println!("\nLive announcers: {}", Announcer::live_count());
+        println!("Scope ending, drops occur in reverse order:");
     }
+    println!("Live announcers after scope: {}", Announcer::live_count());
 
     println!("\n--- Struct fields drop in declaration order ---\n");
 
@@ -218,8 +592,11 @@ fn demo_drop_ordering() {
             field_b: Announcer::new("field_b"),
             field_c: Announcer::new("field_c"),
         };
-        println!("\nStruct scope ending, fields drop in declaration order:");
+        println!("\nLive announcers: {}", Announcer::live_count());
+        println!("Struct scope ending, fields drop in declaration order:");
     }
+    println!("Live announcers after scope: {}", Announcer::live_count());
+    assert_eq!(Announcer::live_count(), 0);
 }
 
 // ============================================================================
@@ -230,6 +607,13 @@ fn demo_drop_ordering() {
 struct Resource {
     name: String,
     children: Vec<Resource>,
+    logged: bool,
+}
+
+thread_local! {
+    /// Resources waiting to be dropped by the outermost `Resource::drop`.
+    static DROP_QUEUE: RefCell<Vec<Resource>> = const { RefCell::new(Vec::new()) };
+    static DRAINING: Cell<bool> = const { Cell::new(false) };
 }
 
 impl Resource {
@@ -238,6 +622,16 @@ impl Resource {
         Resource {
             name: name.to_string(),
             children: Vec::new(),
+            logged: true,
+        }
+    }
+
+    /// A resource that skips lifecycle logging, for building large trees.
+    fn quiet(name: &str) -> Self {
+        Resource {
+            name: name.to_string(),
+            children: Vec::new(),
+            logged: false,
         }
     }
 
@@ -245,13 +639,70 @@ impl Resource {
         self.children.push(child);
         self
     }
+
+    fn find_child(&self, name: &str) -> Option<&Resource> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// Searches the whole subtree below this resource, parents before children.
+    fn find_descendant(&self, name: &str) -> Option<&Resource> {
+        self.descendants().skip(1).find(|node| node.name == name)
+    }
+
+    /// Pre-order walk starting with this resource itself.
+    ///
+    /// Uses an explicit stack rather than recursion, for the same reason
+    /// `drop` does: a deep chain must not overflow the call stack.
+    fn descendants(&self) -> impl Iterator<Item = &Resource> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+
+    /// Number of levels in the tree; a leaf has depth 1.
+    fn depth(&self) -> usize {
+        let mut deepest = 0;
+        let mut stack = vec![(self, 1)];
+        while let Some((node, level)) = stack.pop() {
+            deepest = deepest.max(level);
+            stack.extend(node.children.iter().map(|child| (child, level + 1)));
+        }
+        deepest
+    }
+
+    /// Detaches the named child; its subtree is dropped whenever the caller drops it.
+    fn remove_child(&mut self, name: &str) -> Option<Resource> {
+        let index = self.children.iter().position(|child| child.name == name)?;
+        Some(self.children.remove(index))
+    }
 }
 
+/// Drops iteratively so a very deep tree cannot overflow the stack.
+///
+/// Children are moved onto a thread-local queue instead of being dropped
+/// inside their parent's `drop`. Only the outermost drop drains the queue,
+/// so stack depth stays constant. Pushing in reverse keeps the original
+/// parent-before-children, first-to-last order.
 impl Drop for Resource {
     fn drop(&mut self) {
-        println!("    [Resource '{}' dropping, has {} children]",
-                 self.name, self.children.len());
-        // Children are dropped automatically after this
+        if self.logged {
+            println!("    [Resource '{}' dropping, has {} children]",
+                     self.name, self.children.len());
+        }
+
+        let children = std::mem::take(&mut self.children);
+        DROP_QUEUE.with_borrow_mut(|queue| queue.extend(children.into_iter().rev()));
+
+        if DRAINING.replace(true) {
+            return; // An outer drop is already draining the queue
+        }
+        while let Some(next) = DROP_QUEUE.with_borrow_mut(|queue| queue.pop()) {
+            drop(next);
+        }
+        DRAINING.set(false);
     }
 }
 
@@ -278,11 +729,49 @@ fn demo_ownership_chain_disposal() {
         println!("  │   └── grandchild_1b");
         println!("  └── child_2");
         println!("      └── grandchild_2a");
+
+        let names: Vec<&str> = root.descendants().map(|node| node.name.as_str()).collect();
+        println!("\nPre-order: {:?}", names);
+        println!("Nodes: {}, depth: {} (a leaf alone has depth {})",
+                 names.len(), root.depth(), Resource::quiet("leaf").depth());
         println!("\nDropping root (entire tree will be disposed):");
         drop(root);
     }
 
     println!("\nAll resources freed automatically through ownership chain.");
+
+    println!("\nNavigating and pruning a tree:");
+    {
+        let mut root = Resource::new("root")
+            .with_child(
+                Resource::new("child_1")
+                    .with_child(Resource::new("grandchild_1a"))
+                    .with_child(Resource::new("grandchild_1b"))
+            )
+            .with_child(Resource::new("child_2"));
+
+        println!("\nfind_child(\"grandchild_1a\"): {}",
+                 root.find_child("grandchild_1a").is_some());
+        println!("find_descendant(\"grandchild_1a\"): {}",
+                 root.find_descendant("grandchild_1a").is_some());
+
+        let removed = root.remove_child("child_1").expect("child_1 exists");
+        println!("Removed '{}' with {} children; root now has {}",
+                 removed.name, removed.children.len(), root.children.len());
+        println!("Dropping the removed subtree:");
+        drop(removed);
+        println!("Dropping what is left:");
+    }
+
+    // Recursive drop would need one stack frame per level here
+    let depth = 100_000;
+    let mut chain = Resource::quiet("leaf");
+    for i in 0..depth {
+        chain = Resource::quiet(&format!("level_{}", i)).with_child(chain);
+    }
+    println!("\nDropping a {}-deep chain...", chain.depth());
+    drop(chain);
+    println!("Deep chain dropped without overflowing the stack.");
 }
 
 // ============================================================================
@@ -294,9 +783,100 @@ fn main() {
     println!("==============================");
 
     demo_timer();
+    demo_budget_guard();
     demo_scope_guard();
     demo_drop_ordering();
     demo_ownership_chain_disposal();
 
     println!("\n=== All demos complete ===");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    fn silent(name: &str) -> Timer {
+        Timer::new_with_sink(name, |_, _| {})
+    }
+
+    #[test]
+    fn timer_depth_tracks_nesting() {
+        assert_eq!(timer_depth(), 0);
+        {
+            let _outer = silent("outer");
+            assert_eq!(timer_depth(), 1);
+            {
+                let _inner = silent("inner");
+                assert_eq!(timer_depth(), 2);
+            }
+            assert_eq!(timer_depth(), 1);
+        }
+        assert_eq!(timer_depth(), 0);
+    }
+
+    #[test]
+    fn timer_depth_survives_out_of_order_drops() {
+        let a = silent("a");
+        let b = silent("b");
+        drop(a);
+        assert_eq!(timer_depth(), 1);
+        drop(b);
+        assert_eq!(timer_depth(), 0);
+    }
+
+    #[test]
+    fn timer_depth_restored_on_early_return() {
+        fn work(early: bool) -> u32 {
+            let _timer = silent("work");
+            if early {
+                return 0;
+            }
+            1
+        }
+        work(true);
+        work(false);
+        assert_eq!(timer_depth(), 0);
+    }
+
+    #[test]
+    fn timer_dropped_on_another_thread_restores_its_own_depth() {
+        let timer = silent("moved");
+        assert_eq!(timer_depth(), 1);
+        let depth_there = thread::spawn(move || {
+            drop(timer);
+            timer_depth()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(depth_there, 0);
+        assert_eq!(timer_depth(), 0);
+    }
+
+    #[test]
+    fn guard_stack_runs_cleanups_in_reverse() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        {
+            let mut guards = GuardStack::new();
+            for name in ["first", "second", "third"] {
+                let log = Rc::clone(&log);
+                guards.push(move || log.borrow_mut().push(name));
+            }
+        }
+        assert_eq!(*log.borrow(), ["third", "second", "first"]);
+    }
+
+    #[test]
+    fn disarmed_guard_stack_runs_nothing() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        {
+            let mut guards = GuardStack::new();
+            for name in ["first", "second"] {
+                let log = Rc::clone(&log);
+                guards.push(move || log.borrow_mut().push(name));
+            }
+            guards.disarm_all();
+        }
+        assert!(log.borrow().is_empty());
+    }
+}
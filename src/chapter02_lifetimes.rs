@@ -3,6 +3,9 @@
 //! Demonstrates Rust's lifetime system for ensuring reference validity.
 
 use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::ops::{Deref, Range};
 
 // ============================================================================
 // Functions Returning References
@@ -16,6 +19,51 @@ fn longest<'a>(a: &'a str, b: &'a str) -> &'a str {
     if a.len() > b.len() { a } else { b }
 }
 
+/// Returns the element with the largest key, borrowed from the slice.
+///
+/// The same pattern as `longest`, generalized: the output lives as long as
+/// `items`, whatever `T` is. Ties go to the earliest element; an empty
+/// slice gives `None`. With a single borrowed input, elision already ties
+/// the output to `items`, so no lifetime needs to be written.
+fn longest_by<T>(items: &[T], key: impl Fn(&T) -> usize) -> Option<&T> {
+    items
+        .iter()
+        .reduce(|best, item| if key(item) > key(best) { item } else { best })
+}
+
+/// Returns the longest of any number of string slices.
+///
+/// Every candidate shares `'a`, so the result outlives the slice that
+/// held them. When several share the maximum length the first one wins;
+/// an empty slice gives `None`.
+fn longest_of<'a>(candidates: &[&'a str]) -> Option<&'a str> {
+    longest_by(candidates, |s| s.len()).copied()
+}
+
+/// How `longest_with` resolves two inputs of equal length.
+#[derive(Debug, Clone, Copy)]
+enum TieBreak {
+    PreferFirst,
+    PreferSecond,
+    /// The lexicographically smaller string wins.
+    Lexicographic,
+}
+
+/// Like `longest`, but the tie behavior is chosen by the caller.
+///
+/// `longest` quietly returns `b` on equal lengths; making the policy an
+/// argument turns that hidden detail into a visible decision.
+fn longest_with<'a>(a: &'a str, b: &'a str, tie: TieBreak) -> &'a str {
+    if a.len() != b.len() {
+        return if a.len() > b.len() { a } else { b };
+    }
+    match tie {
+        TieBreak::PreferFirst => a,
+        TieBreak::PreferSecond => b,
+        TieBreak::Lexicographic => a.min(b),
+    }
+}
+
 fn demo_longest() {
     println!("=== Functions Returning References ===\n");
 
@@ -38,6 +86,30 @@ fn demo_longest() {
     }
     // inner is dropped here, so we cannot use a result that might reference it
     println!("After inner scope: outer still valid = {}\n", outer);
+
+    // Equal lengths: the tie-break policy decides
+    for tie in [TieBreak::PreferFirst, TieBreak::PreferSecond, TieBreak::Lexicographic] {
+        println!("longest_with(\"pear\", \"kiwi\", {:?}) = \"{}\"",
+                 tie, longest_with("pear", "kiwi", tie));
+    }
+    println!();
+
+    // The generic version works over any slice; "Grace" and "Linus" tie, so the first wins
+    let names = vec![String::from("Ada"), String::from("Grace"), String::from("Alan"), String::from("Linus")];
+    println!("longest_by(names, len) = {:?}", longest_by(&names, |s| s.len()));
+    let buffers: Vec<Vec<u8>> = vec![vec![1, 2], vec![3, 4, 5], vec![6]];
+    println!("longest_by(buffers, len) = {:?}", longest_by(&buffers, |b| b.len()));
+    let empty: [String; 0] = [];
+    println!("longest_by(empty, len) = {:?}", longest_by(&empty, |s| s.len()));
+
+    let winner = {
+        // The slice is temporary, but the winner borrows from the strings themselves
+        let candidates = [s1.as_str(), s2.as_str(), outer.as_str()];
+        longest_of(&candidates)
+    };
+    println!("longest_of([s1, s2, outer]) = {:?}", winner);
+    println!("longest_of([\"ab\", \"cd\", \"e\"]) = {:?}", longest_of(&["ab", "cd", "e"]));
+    println!("longest_of([]) = {:?}\n", longest_of(&[]));
 }
 
 // ============================================================================
@@ -67,6 +139,22 @@ fn pick_one_borrowed<'a>(x: &'a str, y: &'a str) -> Cow<'a, str> {
     }
 }
 
+/// Collapses whitespace runs to single spaces and trims both ends.
+///
+/// Already-clean input is returned borrowed; only input that actually
+/// needs rewriting pays for an allocation.
+fn normalize_whitespace(s: &str) -> Cow<'_, str> {
+    let clean = !s.starts_with(' ')
+        && !s.ends_with(' ')
+        && !s.contains("  ")
+        && !s.chars().any(|c| c.is_whitespace() && c != ' ');
+    if clean {
+        Cow::Borrowed(s)
+    } else {
+        Cow::Owned(s.split_whitespace().collect::<Vec<_>>().join(" "))
+    }
+}
+
 fn demo_cow() {
     println!("=== Divergent Lifetimes with Cow ===\n");
 
@@ -86,38 +174,132 @@ fn demo_cow() {
     // Cow can be converted to owned String when needed
     let owned_string: String = borrowed_result.into_owned();
     println!("Converted to owned: {}\n", owned_string);
+
+    // A real transformation: allocate only when the input must change
+    for input in ["already clean", "  messy   text ", "tabs\tand\nnewlines"] {
+        let normalized = normalize_whitespace(input);
+        println!("normalize_whitespace({:?}) = {:?} (is_borrowed: {})",
+                 input, normalized, matches!(normalized, Cow::Borrowed(_)));
+    }
+    println!();
 }
 
 // ============================================================================
 // Structs Holding References
 // ============================================================================
 
-/// A struct that holds a reference to string data.
+/// A struct that holds a reference to borrowed data, string data by default.
 ///
-/// The lifetime parameter ensures Holder cannot outlive its data.
+/// The lifetime parameter ensures Holder cannot outlive its data. `?Sized`
+/// lets it point at unsized types like `str` and `[T]`.
 #[derive(Debug)]
-struct Holder<'a> {
-    data: &'a str,
+struct Holder<'a, T: ?Sized = str> {
+    data: &'a T,
 }
 
-impl<'a> Holder<'a> {
-    fn new(data: &'a str) -> Self {
+impl<'a, T: ?Sized> Holder<'a, T> {
+    fn new(data: &'a T) -> Self {
         Holder { data }
     }
 
-    fn get(&self) -> &str {
+    fn get(&self) -> &T {
+        self.data
+    }
+
+    /// Projects to a part of the data without shortening the borrow.
+    ///
+    /// The closure receives `&'a T`, not `&self`, so the result can
+    /// outlive this Holder.
+    fn map<U: ?Sized>(self, f: impl FnOnce(&'a T) -> &'a U) -> Holder<'a, U> {
+        Holder { data: f(self.data) }
+    }
+}
+
+/// Lets a Holder stand in wherever a `&T` is expected.
+///
+/// The target borrows from `self`, and the Holder itself still cannot
+/// outlive `'a`, so the borrow checker guarantees the same as before.
+impl<T: ?Sized> Deref for Holder<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
         self.data
     }
 }
 
+impl<T: ?Sized> AsRef<T> for Holder<'_, T> {
+    fn as_ref(&self) -> &T {
+        self.data
+    }
+}
+
+impl<'a> Holder<'a> {
+
+    /// Whitespace-separated words with their byte ranges in the original data.
+    ///
+    /// The words borrow from the data (`'a`), not from the Holder, so
+    /// `use<'a>` keeps the iterator from capturing `&self`.
+    fn word_spans(&self) -> impl Iterator<Item = (Range<usize>, &'a str)> + use<'a> {
+        let data = self.data;
+        data.split_whitespace().map(move |word| {
+            let start = word.as_ptr() as usize - data.as_ptr() as usize;
+            (start..start + word.len(), word)
+        })
+    }
+}
+
 /// Demonstrates a struct with multiple reference fields.
 #[derive(Debug)]
-#[allow(dead_code)]
 struct Pair<'a, 'b> {
     first: &'a str,
     second: &'b str,
 }
 
+impl<'a, 'b> Pair<'a, 'b> {
+    /// Returns the longer field as owned data.
+    ///
+    /// `'a` and `'b` are unrelated, so there is no single lifetime a borrow
+    /// could carry - the same situation as `pick_one_owned`.
+    fn longer(&self) -> Cow<'static, str> {
+        pick_one_owned(self.first, self.second)
+    }
+
+    /// Swapping the fields swaps the lifetime parameters with them.
+    fn swap(self) -> Pair<'b, 'a> {
+        Pair {
+            first: self.second,
+            second: self.first,
+        }
+    }
+}
+
+/// Two borrows with an invariant between them: `part` lies inside `whole`.
+///
+/// Sharing a lifetime is not enough to guarantee that, so the constructor
+/// checks the pointer ranges and refuses unrelated slices.
+#[derive(Debug)]
+struct SameSource<'a> {
+    whole: &'a str,
+    part: &'a str,
+}
+
+impl<'a> SameSource<'a> {
+    fn new(whole: &'a str, part: &'a str) -> Option<Self> {
+        let whole_range = whole.as_bytes().as_ptr_range();
+        let part_range = part.as_bytes().as_ptr_range();
+        if whole_range.start <= part_range.start && part_range.end <= whole_range.end {
+            Some(SameSource { whole, part })
+        } else {
+            None
+        }
+    }
+
+    /// Byte offset of `part` within `whole`.
+    fn offset(&self) -> usize {
+        self.part.as_ptr() as usize - self.whole.as_ptr() as usize
+    }
+}
+
 fn demo_structs_with_references() {
     println!("=== Structs Holding References ===\n");
 
@@ -128,6 +310,28 @@ fn demo_structs_with_references() {
     println!("Holder contains: {:?}", holder);
     println!("Holder.get(): {}", holder.get());
 
+    // Byte spans stay correct with multibyte characters
+    let source = String::from("naïve  café über");
+    let spans: Vec<_> = Holder::new(source.as_str()).word_spans().collect();
+    for (range, word) in &spans {
+        println!("  {:?} -> {:?} (round-trips: {})", range, word, &source[range.clone()] == *word);
+    }
+
+    // Deref and AsRef: a Holder<str> can be used like a &str
+    let holder = Holder::new(text.as_str());
+    println!("holder.len(): {}, starts_with(\"Hello\"): {}", holder.len(), holder.starts_with("Hello"));
+    assert!(holder.starts_with("Hello"));
+    println!("first_word(&holder): {:?}", first_word(&holder));
+    let as_str: &str = holder.as_ref();
+    println!("as_ref() == &*holder: {}", as_str == &*holder);
+
+    // Holder works with any borrowed type, and map keeps the original lifetime
+    let numbers = vec![10, 20, 30];
+    let first = Holder::new(numbers.as_slice()).map(|slice| &slice[0]);
+    println!("Holder<[i32]> mapped to first element: {}", first.get());
+    let rest = Holder::new(&text).map(|s| &s[7..]);
+    println!("Holder<str> mapped past the comma: {:?}", rest.get());
+
     // Holder must not outlive text
     // If we dropped text here, holder would be invalid
     // drop(text); // This would cause a compile error
@@ -140,7 +344,30 @@ fn demo_structs_with_references() {
         first: &s1,
         second: &s2,
     };
-    println!("Pair: {:?}\n", pair);
+    println!("Pair: {:?}", pair);
+    println!("Swapped: {:?}", pair.swap());
+
+    // The owned result survives the shorter-lived field's scope
+    let long_lived = String::from("short");
+    let longer = {
+        let short_lived = String::from("considerably longer");
+        let pair = Pair { first: &long_lived, second: &short_lived };
+        pair.longer()
+    };
+    println!("Longer after inner scope ended: {:?}\n", longer);
+
+    // Both borrows must come from the same data
+    let word = &text[7..15];
+    match SameSource::new(&text, word) {
+        Some(source) => println!("SameSource: {:?} at offset {}", source.part, source.offset()),
+        None => println!("SameSource rejected"),
+    }
+
+    let unrelated = String::from("lifetime");
+    println!(
+        "Equal text from another String accepted: {}\n",
+        SameSource::new(&text, &unrelated).is_some()
+    );
 }
 
 // ============================================================================
@@ -151,7 +378,14 @@ fn demo_structs_with_references() {
 ///
 /// The compiler infers: fn first_word<'a>(s: &'a str) -> &'a str
 fn first_word(s: &str) -> &str {
-    s.split_whitespace().next().unwrap_or("")
+    words(s).next().unwrap_or("")
+}
+
+/// Whitespace-delimited words - elision works for iterators too.
+///
+/// The compiler infers: fn words<'a>(s: &'a str) -> impl Iterator<Item = &'a str>
+fn words(s: &str) -> impl Iterator<Item = &str> {
+    s.split_whitespace()
 }
 
 /// A document with a title.
@@ -178,12 +412,60 @@ impl Document {
         self.content.len()
     }
 
-    /// Returns a summary - lifetime explicitly tied to self.
-    fn summary(&self) -> &str {
-        if self.content.len() > 50 {
-            &self.content[..50]
-        } else {
-            &self.content
+    /// Returns up to `max_chars` characters - lifetime tied to self.
+    ///
+    /// Counting by `char_indices` keeps the cut on a char boundary, so
+    /// accented letters and emoji never cause a panic.
+    fn summary(&self, max_chars: usize) -> &str {
+        match self.content.char_indices().nth(max_chars) {
+            Some((end, _)) => &self.content[..end],
+            None => &self.content,
+        }
+    }
+
+    fn word_count(&self) -> usize {
+        words(&self.content).count()
+    }
+
+    /// Needs `&mut self`, so no summary borrowed from the old content can
+    /// still be alive when it is replaced.
+    fn replace_content(&mut self, content: &str) {
+        self.content = content.to_string();
+    }
+
+    fn contains(&self, needle: &str) -> bool {
+        self.content.contains(needle)
+    }
+}
+
+/// A document that borrows its title and content instead of owning them.
+///
+/// No allocation happens on construction, but the document cannot outlive
+/// the strings it was built from.
+struct BorrowedDocument<'a> {
+    title: &'a str,
+    content: &'a str,
+}
+
+impl<'a> BorrowedDocument<'a> {
+    fn new(title: &'a str, content: &'a str) -> Self {
+        BorrowedDocument { title, content }
+    }
+
+    /// Returns the title - tied to the borrowed data, not to self.
+    fn title(&self) -> &'a str {
+        self.title
+    }
+
+    fn content_length(&self) -> usize {
+        self.content.len()
+    }
+
+    /// Returns up to `max_chars` characters, cutting on a char boundary.
+    fn summary(&self, max_chars: usize) -> &'a str {
+        match self.content.char_indices().nth(max_chars) {
+            Some((end, _)) => &self.content[..end],
+            None => self.content,
         }
     }
 }
@@ -193,7 +475,16 @@ fn demo_elision() {
 
     let sentence = "Hello world from Rust";
     println!("Sentence: \"{}\"", sentence);
-    println!("First word: \"{}\"\n", first_word(sentence));
+    println!("First word: \"{}\"", first_word(sentence));
+
+    // Every item is a slice of the sentence itself, not a copy
+    let borrowed = words(sentence).all(|w| {
+        let range = sentence.as_bytes().as_ptr_range();
+        range.contains(&w.as_ptr())
+    });
+    println!("Words: {:?} (all borrowed from sentence: {})",
+             words(sentence).collect::<Vec<_>>(), borrowed);
+    println!("Words in \"  \\t \": {}\n", words("  \t ").count());
 
     let doc = Document::new(
         "Rust Lifetimes",
@@ -202,7 +493,25 @@ fn demo_elision() {
 
     println!("Document title: {}", doc.title());
     println!("Content length: {}", doc.content_length());
-    println!("Summary: {}\n", doc.summary());
+    println!("Summary: {}", doc.summary(50));
+    println!("Word count: {}", doc.word_count());
+    println!("Contains \"valid\": {}\n", doc.contains("valid"));
+
+    let mut menu = Document::new("Menu", "café ☕ time");
+    println!("summary(3): {:?}", menu.summary(3));
+    println!("summary(6): {:?}", menu.summary(6));
+    menu.replace_content("Closed for the holidays");
+    println!("After replace: {:?} ({} words, mentions café: {})\n",
+             menu.summary(50), menu.word_count(), menu.contains("café"));
+
+    // Same API, but borrowing instead of copying
+    let title = String::from("Borrowed Lifetimes");
+    let content = String::from("Ünïcödé content is sliced by characters, never through the middle of one.");
+    let borrowed = BorrowedDocument::new(&title, &content);
+
+    println!("Borrowed title: {}", borrowed.title());
+    println!("Borrowed content length: {}", borrowed.content_length());
+    println!("Borrowed summary: {}\n", borrowed.summary(50));
 }
 
 // ============================================================================
@@ -214,6 +523,31 @@ fn static_string() -> &'static str {
     "I am embedded in the binary"
 }
 
+/// Turns runtime strings into `&'static str`, one allocation per distinct value.
+///
+/// Each new string is leaked on purpose: memory that is never freed is
+/// exactly what `'static` promises. That is fine for a bounded set like
+/// identifiers or config keys, but not for unbounded user input.
+struct Interner {
+    strings: Mutex<HashSet<&'static str>>,
+}
+
+impl Interner {
+    fn new() -> Self {
+        Interner { strings: Mutex::new(HashSet::new()) }
+    }
+
+    fn intern(&self, s: &str) -> &'static str {
+        let mut strings = self.strings.lock().unwrap();
+        if let Some(&existing) = strings.get(s) {
+            return existing;
+        }
+        let leaked: &'static str = Box::leak(s.to_owned().into_boxed_str());
+        strings.insert(leaked);
+        leaked
+    }
+}
+
 /// Demonstrates thread spawning with 'static requirement.
 fn demo_static_lifetime() {
     println!("=== The 'static Lifetime ===\n");
@@ -236,6 +570,17 @@ fn demo_static_lifetime() {
 
     let result = handle.join().unwrap();
     println!("Thread returned: {}\n", result);
+
+    // Interned strings are 'static, so threads can borrow them without moving
+    let interner = Interner::new();
+    let id = 42;
+    let name = format!("worker-{}", id); // Built at runtime, so not 'static itself
+    let first = interner.intern(&name);
+    let second = interner.intern("worker-42");
+    println!("Interned twice, same pointer: {}", std::ptr::eq(first, second));
+
+    let handle = std::thread::spawn(move || format!("{} reporting", first));
+    println!("Thread with interned name: {}\n", handle.join().unwrap());
 }
 
 // ============================================================================
@@ -301,3 +646,135 @@ fn main() {
 
     println!("=== All lifetime demos complete ===");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_prefers_second_on_ties() {
+        assert_eq!(longest("long string", "short"), "long string");
+        assert_eq!(longest("abc", "xyz"), "xyz");
+    }
+
+    #[test]
+    fn longest_by_first_max_wins_and_empty_is_none() {
+        let items = ["bb", "a", "cc", "d"];
+        assert_eq!(longest_by(&items, |s| s.len()), Some(&"bb"));
+        assert_eq!(longest_by(&[] as &[&str], |s| s.len()), None);
+        assert_eq!(longest_of(&["x", "yyy", "zzz"]), Some("yyy"));
+        assert_eq!(longest_of(&[]), None);
+    }
+
+    #[test]
+    fn longest_with_applies_the_tie_break() {
+        assert_eq!(longest_with("abc", "xyz", TieBreak::PreferFirst), "abc");
+        assert_eq!(longest_with("abc", "xyz", TieBreak::PreferSecond), "xyz");
+        assert_eq!(longest_with("xyz", "abc", TieBreak::Lexicographic), "abc");
+        assert_eq!(longest_with("ab", "xyz", TieBreak::PreferFirst), "xyz");
+    }
+
+    #[test]
+    fn normalize_whitespace_borrows_clean_input() {
+        let clean = normalize_whitespace("already clean");
+        assert!(matches!(clean, Cow::Borrowed(_)));
+        let messy = normalize_whitespace("  messy   text ");
+        assert!(matches!(messy, Cow::Owned(_)));
+        assert_eq!(messy, "messy text");
+        assert_eq!(normalize_whitespace("  a \t b\nc "), "a b c");
+    }
+
+    #[test]
+    fn words_borrow_from_the_input() {
+        let text = String::from("  alpha beta ");
+        let found: Vec<&str> = words(&text).collect();
+        assert_eq!(found, ["alpha", "beta"]);
+        assert!(text.as_bytes().as_ptr_range().contains(&found[0].as_ptr()));
+        assert_eq!(words(" \t\n ").count(), 0);
+        assert_eq!(first_word(&text), "alpha");
+        assert_eq!(first_word("   "), "");
+    }
+
+    #[test]
+    fn pair_longer_clones_across_scopes_and_swap_swaps() {
+        let first = String::from("a much longer string");
+        let longer = {
+            let second = String::from("short");
+            Pair {
+                first: &first,
+                second: &second,
+            }
+            .longer()
+        };
+        assert_eq!(longer, "a much longer string");
+
+        let swapped = Pair {
+            first: "one",
+            second: "two",
+        }
+        .swap();
+        assert_eq!((swapped.first, swapped.second), ("two", "one"));
+    }
+
+    #[test]
+    fn holder_map_outlives_the_holder_and_derefs() {
+        let text = String::from("Hello, lifetime world");
+        let tail = {
+            let holder = Holder::new(text.as_str());
+            assert!(holder.starts_with("Hello"));
+            assert_eq!(holder.len(), text.len());
+            holder.map(|s| &s[7..])
+        };
+        assert_eq!(&*tail, "lifetime world");
+        assert_eq!(tail.get(), "lifetime world");
+
+        let numbers = [3, 1, 4];
+        let first = Holder::new(&numbers[..]).map(|n| &n[0]);
+        assert_eq!(*first, 3);
+    }
+
+    #[test]
+    fn word_spans_round_trip_multibyte_text() {
+        let source = "naïve  café über";
+        let spans: Vec<_> = Holder::new(source).word_spans().collect();
+        assert_eq!(spans.len(), 3);
+        for (range, word) in &spans {
+            assert_eq!(&source[range.clone()], *word);
+        }
+        assert_eq!(spans[1].0, 8..13);
+    }
+
+    #[test]
+    fn same_source_accepts_only_subslices() {
+        let whole = "hello world";
+        let part = SameSource::new(whole, &whole[6..]).unwrap();
+        assert_eq!(part.offset(), 6);
+        let other = String::from("world");
+        assert!(SameSource::new(whole, &other).is_none());
+    }
+
+    #[test]
+    fn summaries_cut_on_char_boundaries() {
+        let content = "Ünïcödé text";
+        let owned = Document::new("t", content);
+        assert_eq!(owned.summary(3), "Ünï");
+        assert_eq!(owned.summary(100), content);
+        assert_eq!(owned.summary(0), "");
+
+        let title = String::from("Title");
+        let borrowed = BorrowedDocument::new(&title, content);
+        assert_eq!(borrowed.title(), "Title");
+        assert_eq!(borrowed.content_length(), content.len());
+        assert_eq!(borrowed.summary(7), "Ünïcödé");
+        assert_eq!(borrowed.summary(100), content);
+    }
+
+    #[test]
+    fn interner_returns_the_same_allocation_per_value() {
+        let interner = Interner::new();
+        let a = interner.intern(&String::from("key"));
+        let b = interner.intern("key");
+        assert!(std::ptr::eq(a, b));
+        assert_ne!(interner.intern("other"), a);
+    }
+}
@@ -3,6 +3,9 @@
 //! This example demonstrates modeling a domain with structs and enums,
 //! showing how Rust's type system makes invalid states unrepresentable.
 
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
 use std::time::SystemTime;
 
 // Tuple structs for type-safe IDs
@@ -15,26 +18,76 @@ struct CustomerId(u64);
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct ProductId(u64);
 
+// Hands out unique, increasing ids; safe to share between threads
+struct IdGenerator {
+    next: AtomicU64,
+}
+
+impl IdGenerator {
+    fn starting_at(base: u64) -> Self {
+        Self {
+            next: AtomicU64::new(base),
+        }
+    }
+
+    // Relaxed is enough: uniqueness comes from the atomic read-modify-write itself
+    fn next(&self) -> u64 {
+        self.next.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn next_order_id(&self) -> OrderId {
+        OrderId(self.next())
+    }
+
+    fn next_customer_id(&self) -> CustomerId {
+        CustomerId(self.next())
+    }
+}
+
 // Order item with product details
 #[derive(Debug, Clone)]
 struct OrderItem {
     product_id: ProductId,
     quantity: u32,
     unit_price: u64, // Price in cents
+    weight_grams: u32, // Weight of a single unit
 }
 
 impl OrderItem {
-    fn new(product_id: ProductId, quantity: u32, unit_price: u64) -> Self {
-        Self {
+    fn try_new(
+        product_id: ProductId,
+        quantity: u32,
+        unit_price: u64,
+    ) -> Result<Self, &'static str> {
+        if quantity == 0 {
+            return Err("Quantity must be at least 1");
+        }
+        Ok(Self {
             product_id,
             quantity,
             unit_price,
-        }
+            weight_grams: 0,
+        })
+    }
+
+    // Convenience for known-good literals; panics on invalid input
+    fn new(product_id: ProductId, quantity: u32, unit_price: u64) -> Self {
+        Self::try_new(product_id, quantity, unit_price).expect("invalid order item")
+    }
+
+    fn with_weight(mut self, weight_grams: u32) -> Self {
+        self.weight_grams = weight_grams;
+        self
     }
 
     fn total(&self) -> u64 {
         self.quantity as u64 * self.unit_price
     }
+
+    // Widened before multiplying: a u32 quantity of u32 grams always fits in u64
+    fn total_weight(&self) -> u64 {
+        self.quantity as u64 * self.weight_grams as u64
+    }
 }
 
 // Shipping information (only relevant when shipped)
@@ -56,12 +109,44 @@ struct DeliveryInfo {
 #[derive(Debug, Clone)]
 enum OrderStatus {
     Pending,
+    PartiallyShipped {
+        shipped_items: Vec<ProductId>,
+        shipping: ShippingInfo, // Most recent package
+    },
     Shipped(ShippingInfo),
     Delivered {
         shipping: ShippingInfo,
         delivery: DeliveryInfo,
     },
     Cancelled(String), // Reason for cancellation
+    Returned {
+        shipping: ShippingInfo, // Kept so the return can still be traced
+        reason: String,
+        returned_at: SystemTime,
+    },
+}
+
+// A coupon applied to the whole order
+#[derive(Debug, Clone, Copy)]
+enum Discount {
+    Percentage(u8), // Values over 100 are allowed and clamp the total to zero
+    FixedCents(u64),
+}
+
+// Everything that can happen to an order, in the order it happened
+#[derive(Debug, Clone)]
+enum OrderEvent {
+    AddItem(OrderItem),
+    Ship {
+        carrier: String,
+        tracking_number: String,
+    },
+    Deliver {
+        signature: Option<String>,
+    },
+    Cancel {
+        reason: String,
+    },
 }
 
 // The order struct
@@ -71,6 +156,10 @@ struct Order {
     customer_id: CustomerId,
     items: Vec<OrderItem>,
     status: OrderStatus,
+    idempotency_keys: HashSet<String>, // Keys of operations already applied
+    refunded_total_cents: u64,
+    refunds: Vec<(u64, String)>, // Amount in cents and reason, oldest first
+    discount: Option<Discount>,
 }
 
 impl Order {
@@ -80,21 +169,124 @@ impl Order {
             customer_id,
             items: Vec::new(),
             status: OrderStatus::Pending,
+            idempotency_keys: HashSet::new(),
+            refunded_total_cents: 0,
+            refunds: Vec::new(),
+            discount: None,
         }
     }
 
-    fn add_item(&mut self, item: OrderItem) {
-        self.items.push(item);
+    // Rebuilds an order from its event log, rejecting any illegal transition
+    fn replay(
+        id: OrderId,
+        customer_id: CustomerId,
+        events: &[OrderEvent],
+    ) -> Result<Order, &'static str> {
+        let mut order = Order::new(id, customer_id);
+        for event in events.iter().cloned() {
+            match event {
+                OrderEvent::AddItem(item) => order.add_item(item)?,
+                OrderEvent::Ship {
+                    carrier,
+                    tracking_number,
+                } => order.ship(carrier, tracking_number, None)?,
+                OrderEvent::Deliver { signature } => order.deliver(signature, None)?,
+                OrderEvent::Cancel { reason } => order.cancel(reason, None)?,
+            }
+        }
+        Ok(order)
     }
 
-    fn total(&self) -> u64 {
+    // Items and discounts are only editable before shipping
+    fn ensure_pending(&self) -> Result<(), &'static str> {
+        match self.status {
+            OrderStatus::Pending => Ok(()),
+            _ => Err("Can only change pending orders"),
+        }
+    }
+
+    // The same product at the same price becomes one line with a larger quantity
+    fn add_item(&mut self, item: OrderItem) -> Result<(), &'static str> {
+        self.ensure_pending()?;
+        let existing = self.items.iter_mut().find(|existing| {
+            existing.product_id == item.product_id && existing.unit_price == item.unit_price
+        });
+        match existing {
+            Some(existing) => existing.quantity += item.quantity,
+            None => self.items.push(item),
+        }
+        Ok(())
+    }
+
+    // Removes every line for the product
+    fn remove_item(&mut self, product_id: ProductId) -> Result<(), &'static str> {
+        self.ensure_pending()?;
+        let before = self.items.len();
+        self.items.retain(|item| item.product_id != product_id);
+        if self.items.len() == before {
+            return Err("Product is not in this order");
+        }
+        Ok(())
+    }
+
+    fn subtotal(&self) -> u64 {
         self.items.iter().map(|item| item.total()).sum()
     }
 
-    fn ship(&mut self, carrier: String, tracking_number: String) -> Result<(), &'static str> {
-        match &self.status {
+    // Saturates at zero, so an oversized discount can never underflow
+    fn total(&self) -> u64 {
+        let subtotal = self.subtotal();
+        let off = match self.discount {
+            None => 0,
+            // Widened so the multiplication cannot overflow; anything past u64 clamps to zero anyway
+            Some(Discount::Percentage(percent)) => {
+                u64::try_from(subtotal as u128 * percent as u128 / 100).unwrap_or(u64::MAX)
+            }
+            Some(Discount::FixedCents(cents)) => cents,
+        };
+        subtotal.saturating_sub(off)
+    }
+
+    // Replaces any earlier discount; coupons do not stack
+    fn apply_discount(&mut self, discount: Discount) -> Result<(), &'static str> {
+        self.ensure_pending()?;
+        self.discount = Some(discount);
+        Ok(())
+    }
+
+    fn total_weight(&self) -> u64 {
+        self.items.iter().map(|item| item.total_weight()).sum()
+    }
+
+    // Rate is in cents per kilogram; partial kilograms round up to the next cent
+    fn estimate_shipping_cents(&self, rate_per_kg: u64) -> u64 {
+        (self.total_weight() * rate_per_kg).div_ceil(1000)
+    }
+
+    // Runs a transition at most once per key; a retried key is a successful no-op
+    fn idempotent<F>(&mut self, key: Option<&str>, transition: F) -> Result<(), &'static str>
+    where
+        F: FnOnce(&mut Self) -> Result<(), &'static str>,
+    {
+        if key.is_some_and(|k| self.idempotency_keys.contains(k)) {
+            return Ok(());
+        }
+        transition(self)?;
+        if let Some(k) = key {
+            self.idempotency_keys.insert(k.to_string());
+        }
+        Ok(())
+    }
+
+    fn ship(
+        &mut self,
+        carrier: String,
+        tracking_number: String,
+        idempotency_key: Option<&str>,
+    ) -> Result<(), &'static str> {
+        self.idempotent(idempotency_key, |order| match &order.status {
             OrderStatus::Pending => {
-                self.status = OrderStatus::Shipped(ShippingInfo {
+                order.status = OrderStatus::Shipped(ShippingInfo {
                     carrier,
                     tracking_number,
                     shipped_at: SystemTime::now(),
@@ -102,13 +294,62 @@ impl Order {
                 Ok(())
             }
             _ => Err("Can only ship pending orders"),
+        })
+    }
+
+    // Ships some products now; the order becomes Shipped once every product has gone out
+    fn ship_items(
+        &mut self,
+        items: &[ProductId],
+        carrier: String,
+        tracking_number: String,
+    ) -> Result<(), &'static str> {
+        let mut shipped_items = match &self.status {
+            OrderStatus::Pending => Vec::new(),
+            OrderStatus::PartiallyShipped { shipped_items, .. } => shipped_items.clone(),
+            _ => return Err("Can only ship items of pending or partially shipped orders"),
+        };
+        if items.is_empty() {
+            return Err("A package must contain at least one product");
+        }
+        for &product_id in items {
+            if !self.items.iter().any(|item| item.product_id == product_id) {
+                return Err("Product is not in this order");
+            }
+            if shipped_items.contains(&product_id) {
+                return Err("Product has already been shipped");
+            }
+            shipped_items.push(product_id);
         }
+
+        let shipping = ShippingInfo {
+            carrier,
+            tracking_number,
+            shipped_at: SystemTime::now(),
+        };
+        let all_shipped = self
+            .items
+            .iter()
+            .all(|item| shipped_items.contains(&item.product_id));
+        self.status = if all_shipped {
+            OrderStatus::Shipped(shipping)
+        } else {
+            OrderStatus::PartiallyShipped {
+                shipped_items,
+                shipping,
+            }
+        };
+        Ok(())
     }
 
-    fn deliver(&mut self, signature: Option<String>) -> Result<(), &'static str> {
-        match &self.status {
+    fn deliver(
+        &mut self,
+        signature: Option<String>,
+        idempotency_key: Option<&str>,
+    ) -> Result<(), &'static str> {
+        self.idempotent(idempotency_key, |order| match &order.status {
             OrderStatus::Shipped(shipping) => {
-                self.status = OrderStatus::Delivered {
+                order.status = OrderStatus::Delivered {
                     shipping: shipping.clone(),
                     delivery: DeliveryInfo {
                         delivered_at: SystemTime::now(),
@@ -118,26 +359,71 @@ impl Order {
                 Ok(())
             }
             _ => Err("Can only deliver shipped orders"),
-        }
+        })
     }
 
-    fn cancel(&mut self, reason: String) -> Result<(), &'static str> {
-        match &self.status {
+    fn cancel(
+        &mut self,
+        reason: String,
+        idempotency_key: Option<&str>,
+    ) -> Result<(), &'static str> {
+        self.idempotent(idempotency_key, |order| match &order.status {
             OrderStatus::Pending => {
-                self.status = OrderStatus::Cancelled(reason);
+                order.status = OrderStatus::Cancelled(reason);
                 Ok(())
             }
+            OrderStatus::PartiallyShipped { .. } => Err("Cannot cancel partially shipped orders"),
             OrderStatus::Shipped(_) => Err("Cannot cancel shipped orders"),
             OrderStatus::Delivered { .. } => Err("Cannot cancel delivered orders"),
             OrderStatus::Cancelled(_) => Err("Order already cancelled"),
+            OrderStatus::Returned { .. } => Err("Cannot cancel returned orders"),
+        })
+    }
+
+    fn return_order(&mut self, reason: String) -> Result<(), &'static str> {
+        match &self.status {
+            OrderStatus::Delivered { shipping, .. } => {
+                self.status = OrderStatus::Returned {
+                    shipping: shipping.clone(),
+                    reason,
+                    returned_at: SystemTime::now(),
+                };
+                Ok(())
+            }
+            OrderStatus::Pending => Err("Cannot return an order that has not shipped"),
+            OrderStatus::PartiallyShipped { .. } | OrderStatus::Shipped(_) => {
+                Err("Cannot return an order before it is delivered")
+            }
+            OrderStatus::Cancelled(_) => Err("Cannot return a cancelled order"),
+            OrderStatus::Returned { .. } => Err("Order already returned"),
+        }
+    }
+
+    // Partial refunds accumulate; together they can never exceed the order total
+    fn refund(&mut self, amount_cents: u64, reason: String) -> Result<(), &'static str> {
+        match self.status {
+            OrderStatus::Delivered { .. } | OrderStatus::Returned { .. } => {}
+            _ => return Err("Can only refund delivered or returned orders"),
+        }
+        if amount_cents == 0 {
+            return Err("Refund amount must be positive");
         }
+        self.refunded_total_cents = self
+            .refunded_total_cents
+            .checked_add(amount_cents)
+            .filter(|total| *total <= self.total())
+            .ok_or("Refund would exceed the order total")?;
+        self.refunds.push((amount_cents, reason));
+        Ok(())
     }
 
     // Returns tracking number only if order has been shipped
     fn tracking_number(&self) -> Option<&str> {
         match &self.status {
+            OrderStatus::PartiallyShipped { shipping, .. } => Some(&shipping.tracking_number),
             OrderStatus::Shipped(info) => Some(&info.tracking_number),
             OrderStatus::Delivered { shipping, .. } => Some(&shipping.tracking_number),
+            OrderStatus::Returned { shipping, .. } => Some(&shipping.tracking_number),
             _ => None,
         }
     }
@@ -145,6 +431,16 @@ impl Order {
     fn status_description(&self) -> String {
         match &self.status {
             OrderStatus::Pending => "Pending".to_string(),
+            OrderStatus::PartiallyShipped {
+                shipped_items,
+                shipping,
+            } => format!(
+                "Partially shipped ({} of {} products), last via {} ({})",
+                shipped_items.len(),
+                self.items.len(),
+                shipping.carrier,
+                shipping.tracking_number
+            ),
             OrderStatus::Shipped(info) => {
                 format!("Shipped via {} ({})", info.carrier, info.tracking_number)
             }
@@ -157,6 +453,7 @@ impl Order {
                 format!("Delivered{}", sig)
             }
             OrderStatus::Cancelled(reason) => format!("Cancelled: {}", reason),
+            OrderStatus::Returned { reason, .. } => format!("Returned: {}", reason),
         }
     }
 }
@@ -166,26 +463,51 @@ fn main() {
     let mut order = Order::new(OrderId(1001), CustomerId(42));
 
     // Add items
-    order.add_item(OrderItem::new(ProductId(101), 2, 2999)); // 2x $29.99
-    order.add_item(OrderItem::new(ProductId(102), 1, 4999)); // 1x $49.99
+    let items = [
+        OrderItem::new(ProductId(101), 1, 2999).with_weight(450), // 1x $29.99
+        OrderItem::new(ProductId(102), 1, 4999).with_weight(1200), // 1x $49.99
+        OrderItem::new(ProductId(101), 1, 2999).with_weight(450), // Same product again
+        OrderItem::new(ProductId(199), 1, 99),                    // Added by mistake
+    ];
+    for item in items {
+        order.add_item(item).expect("New orders accept items");
+    }
+    order
+        .remove_item(ProductId(199))
+        .expect("Product 199 was added above");
+    for item in &order.items {
+        println!(
+            "{:?} x{} @ {}",
+            item.product_id, item.quantity, item.unit_price
+        );
+    }
 
     println!("Order {:?}", order.id);
     println!("Customer: {:?}", order.customer_id);
     println!("Total: ${:.2}", order.total() as f64 / 100.0);
+    println!("Weight: {} g", order.total_weight());
+    println!(
+        "Shipping at $8.50/kg: ${:.2}",
+        order.estimate_shipping_cents(850) as f64 / 100.0
+    );
     println!("Status: {}", order.status_description());
     println!("Tracking: {:?}", order.tracking_number());
 
     // Ship the order
     println!("\n--- Shipping order ---");
     order
-        .ship("FedEx".to_string(), "FX123456789".to_string())
+        .ship("FedEx".to_string(), "FX123456789".to_string(), None)
         .expect("Should ship successfully");
     println!("Status: {}", order.status_description());
     println!("Tracking: {:?}", order.tracking_number());
+    println!(
+        "Removing an item after shipping: {:?}",
+        order.remove_item(ProductId(101))
+    );
 
     // Try to cancel (should fail)
     println!("\n--- Attempting to cancel shipped order ---");
-    match order.cancel("Changed my mind".to_string()) {
+    match order.cancel("Changed my mind".to_string(), None) {
         Ok(()) => println!("Cancelled"),
         Err(e) => println!("Cannot cancel: {}", e),
     }
@@ -193,16 +515,344 @@ fn main() {
     // Deliver the order
     println!("\n--- Delivering order ---");
     order
-        .deliver(Some("John Doe".to_string()))
+        .deliver(Some("John Doe".to_string()), None)
         .expect("Should deliver successfully");
     println!("Status: {}", order.status_description());
 
+    // Partial refunds on the delivered order
+    println!("\n--- Refunding order ---");
+    for (amount, reason) in [
+        (2000, "Damaged box"),
+        (3000, "Late delivery"),
+        (7000, "Goodwill"),
+    ] {
+        match order.refund(amount, reason.to_string()) {
+            Ok(()) => println!("Refunded ${:.2} ({})", amount as f64 / 100.0, reason),
+            Err(e) => println!("Refund of ${:.2} rejected: {}", amount as f64 / 100.0, e),
+        }
+    }
+    println!(
+        "Refunded ${:.2} of ${:.2} in {} refund(s)",
+        order.refunded_total_cents as f64 / 100.0,
+        order.total() as f64 / 100.0,
+        order.refunds.len()
+    );
+
+    // Return the delivered order
+    println!("\n--- Returning order ---");
+    order
+        .return_order("Wrong size".to_string())
+        .expect("Should return successfully");
+    println!("Status: {}", order.status_description());
+    println!("Tracking: {:?}", order.tracking_number());
+    if let OrderStatus::Returned { returned_at, .. } = &order.status {
+        let age = returned_at.elapsed().unwrap_or_default();
+        println!("Returned {:?} ago", age);
+    }
+    println!(
+        "Refund after return: {:?}",
+        order.refund(1000, "Restocking credit".to_string())
+    );
+    println!(
+        "Returning again: {:?}",
+        order.return_order("Still wrong".to_string())
+    );
+
     // Demonstrate a cancelled order
     println!("\n--- Creating and cancelling another order ---");
     let mut order2 = Order::new(OrderId(1002), CustomerId(42));
-    order2.add_item(OrderItem::new(ProductId(103), 1, 9999));
     order2
-        .cancel("Out of stock".to_string())
+        .add_item(OrderItem::new(ProductId(103), 1, 9999))
+        .expect("New orders accept items");
+    order2
+        .cancel("Out of stock".to_string(), None)
         .expect("Should cancel successfully");
     println!("Order 1002 status: {}", order2.status_description());
+    println!(
+        "Refund on cancelled order: {:?}",
+        order2.refund(500, "Customer request".to_string())
+    );
+    println!(
+        "Return of cancelled order: {:?}",
+        order2.return_order("Never arrived".to_string())
+    );
+
+    // Retried requests carry the same idempotency key
+    println!("\n--- Idempotent shipping ---");
+    let mut order3 = Order::new(OrderId(1003), CustomerId(7));
+    order3
+        .add_item(OrderItem::new(ProductId(104), 3, 1299))
+        .expect("New orders accept items");
+    for key in ["ship-req-1", "ship-req-1", "ship-req-2"] {
+        let result = order3.ship("UPS".to_string(), "1Z999".to_string(), Some(key));
+        println!("ship with key {}: {:?}", key, result);
+    }
+    println!("Order 1003 status: {}", order3.status_description());
+    println!(
+        "Return of shipped order: {:?}",
+        order3.return_order("Too slow".to_string())
+    );
+    let mut pending = Order::new(OrderId(1007), CustomerId(7));
+    println!(
+        "Return of pending order: {:?}",
+        pending.return_order("Changed my mind".to_string())
+    );
+
+    // One order, two packages
+    println!("\n--- Shipping in packages ---");
+    let mut split = Order::new(OrderId(1008), CustomerId(7));
+    for item in [
+        OrderItem::new(ProductId(111), 1, 1500),
+        OrderItem::new(ProductId(112), 2, 800),
+        OrderItem::new(ProductId(113), 1, 2500),
+    ] {
+        split.add_item(item).expect("New orders accept items");
+    }
+    let packages: [(&[ProductId], &str); 4] = [
+        (&[ProductId(111), ProductId(112)], "PKG-1"),
+        (&[ProductId(999)], "PKG-X"),
+        (&[ProductId(112)], "PKG-Y"),
+        (&[ProductId(113)], "PKG-2"),
+    ];
+    for (products, tracking) in packages {
+        let result = split.ship_items(products, "USPS".to_string(), tracking.to_string());
+        println!("ship {:?} as {}: {:?}", products, tracking, result);
+        println!("  Status: {}", split.status_description());
+    }
+
+    // Coupons
+    println!("\n--- Applying discounts ---");
+    let mut discounted = Order::new(OrderId(1009), CustomerId(7));
+    discounted
+        .add_item(OrderItem::new(ProductId(114), 2, 2500))
+        .expect("New orders accept items");
+    println!("Subtotal: ${:.2}", discounted.subtotal() as f64 / 100.0);
+    for discount in [
+        Discount::Percentage(20),
+        Discount::FixedCents(1250),
+        Discount::Percentage(150),
+        Discount::FixedCents(10_000),
+    ] {
+        discounted
+            .apply_discount(discount)
+            .expect("Pending orders accept discounts");
+        println!(
+            "{:?}: total ${:.2}",
+            discount,
+            discounted.total() as f64 / 100.0
+        );
+    }
+    discounted
+        .ship("UPS".to_string(), "1Z999".to_string(), None)
+        .expect("Should ship successfully");
+    println!(
+        "Discount after shipping: {:?}",
+        discounted.apply_discount(Discount::Percentage(10))
+    );
+
+    // Unique ids from several threads at once
+    println!("\n--- Generating ids ---");
+    let order_ids = IdGenerator::starting_at(2000);
+    let customer_ids = IdGenerator::starting_at(500);
+    let mut ids: Vec<u64> = thread::scope(|s| {
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                s.spawn(|| {
+                    (0..250)
+                        .map(|_| order_ids.next_order_id().0)
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect()
+    });
+    ids.sort_unstable();
+    let contiguous = ids.iter().copied().eq(2000..2000 + ids.len() as u64);
+    println!(
+        "{} order ids, contiguous with no duplicates: {}",
+        ids.len(),
+        contiguous
+    );
+    let mut next_order = Order::new(order_ids.next_order_id(), customer_ids.next_customer_id());
+    next_order
+        .add_item(OrderItem::new(ProductId(110), 1, 100))
+        .expect("New orders accept items");
+    println!(
+        "Next order: {:?} for {:?}",
+        next_order.id, next_order.customer_id
+    );
+
+    // Validated construction
+    println!("\n--- Validating items ---");
+    println!(
+        "1 x $5.00: {:?}",
+        OrderItem::try_new(ProductId(109), 1, 500).map(|item| item.total())
+    );
+    println!(
+        "0 x $5.00: {:?}",
+        OrderItem::try_new(ProductId(109), 0, 500).map(|item| item.total())
+    );
+
+    // Rebuild orders from their event logs
+    println!("\n--- Replaying events ---");
+    let events = vec![
+        OrderEvent::AddItem(OrderItem::new(ProductId(105), 1, 1999)),
+        OrderEvent::Ship {
+            carrier: "DHL".to_string(),
+            tracking_number: "DHL42".to_string(),
+        },
+        OrderEvent::Deliver {
+            signature: Some("Jane Roe".to_string()),
+        },
+    ];
+    match Order::replay(OrderId(1004), CustomerId(9), &events) {
+        Ok(order) => println!(
+            "Order 1004 rebuilt: {} (total ${:.2})",
+            order.status_description(),
+            order.total() as f64 / 100.0
+        ),
+        Err(e) => println!("Replay failed: {}", e),
+    }
+
+    let out_of_order = vec![
+        OrderEvent::AddItem(OrderItem::new(ProductId(106), 2, 500)),
+        OrderEvent::Deliver { signature: None },
+        OrderEvent::Ship {
+            carrier: "DHL".to_string(),
+            tracking_number: "DHL43".to_string(),
+        },
+    ];
+    match Order::replay(OrderId(1005), CustomerId(9), &out_of_order) {
+        Ok(order) => println!("Order 1005 rebuilt: {}", order.status_description()),
+        Err(e) => println!("Order 1005 replay rejected: {}", e),
+    }
+
+    let after_cancel = vec![
+        OrderEvent::AddItem(OrderItem::new(ProductId(107), 1, 750)),
+        OrderEvent::Cancel {
+            reason: "Duplicate order".to_string(),
+        },
+        OrderEvent::AddItem(OrderItem::new(ProductId(108), 1, 250)),
+    ];
+    match Order::replay(OrderId(1006), CustomerId(9), &after_cancel) {
+        Ok(order) => println!("Order 1006 rebuilt: {}", order.status_description()),
+        Err(e) => println!("Order 1006 replay rejected: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delivered_order(unit_price: u64) -> Order {
+        let mut order = Order::new(OrderId(1), CustomerId(1));
+        order
+            .add_item(OrderItem::new(ProductId(1), 1, unit_price))
+            .unwrap();
+        order
+            .ship("UPS".to_string(), "1Z".to_string(), None)
+            .unwrap();
+        order.deliver(None, None).unwrap();
+        order
+    }
+
+    #[test]
+    fn refunds_accumulate_up_to_the_total() {
+        let mut order = delivered_order(5000);
+        assert_eq!(order.refund(3000, "Damaged".to_string()), Ok(()));
+        assert_eq!(order.refund(2000, "Late".to_string()), Ok(()));
+        assert_eq!(
+            order.refund(1, "Goodwill".to_string()),
+            Err("Refund would exceed the order total")
+        );
+        assert_eq!(order.refunded_total_cents, 5000);
+        assert_eq!(order.refunds.len(), 2);
+    }
+
+    #[test]
+    fn oversized_refund_is_rejected_without_overflow() {
+        let mut order = delivered_order(5000);
+        order.refund(100, "Damaged".to_string()).unwrap();
+        assert_eq!(
+            order.refund(u64::MAX, "Typo".to_string()),
+            Err("Refund would exceed the order total")
+        );
+        assert_eq!(order.refunded_total_cents, 100);
+        assert_eq!(order.refunds.len(), 1);
+    }
+
+    fn pending_order(unit_price: u64) -> Order {
+        let mut order = Order::new(OrderId(1), CustomerId(1));
+        order
+            .add_item(OrderItem::new(ProductId(1), 2, unit_price))
+            .unwrap();
+        order
+    }
+
+    #[test]
+    fn discounts_reduce_the_total() {
+        let mut order = pending_order(2500);
+        order.apply_discount(Discount::Percentage(20)).unwrap();
+        assert_eq!(order.total(), 4000);
+        order.apply_discount(Discount::FixedCents(1250)).unwrap();
+        assert_eq!(order.total(), 3750);
+    }
+
+    #[test]
+    fn oversized_discounts_clamp_to_zero() {
+        let mut order = pending_order(2500);
+        order.apply_discount(Discount::Percentage(150)).unwrap();
+        assert_eq!(order.total(), 0);
+        order.apply_discount(Discount::FixedCents(10_000)).unwrap();
+        assert_eq!(order.total(), 0);
+    }
+
+    #[test]
+    fn percentage_of_a_huge_subtotal_does_not_overflow() {
+        let mut order = pending_order(u64::MAX / 2);
+        order.apply_discount(Discount::Percentage(50)).unwrap();
+        assert_eq!(order.total(), order.subtotal() - order.subtotal() / 2);
+    }
+
+    #[test]
+    fn discount_is_rejected_after_shipping() {
+        let mut order = pending_order(2500);
+        order
+            .ship("UPS".to_string(), "1Z".to_string(), None)
+            .unwrap();
+        assert!(order.apply_discount(Discount::Percentage(10)).is_err());
+        assert_eq!(order.total(), 5000);
+    }
+
+    #[test]
+    fn shipping_rounds_partial_kilograms_up() {
+        let mut order = Order::new(OrderId(1), CustomerId(1));
+        order
+            .add_item(OrderItem::new(ProductId(1), 2, 100).with_weight(450))
+            .unwrap();
+        order
+            .add_item(OrderItem::new(ProductId(2), 1, 100).with_weight(1201))
+            .unwrap();
+        assert_eq!(order.total_weight(), 2101);
+        // 2.101 kg at 850 cents/kg is 1785.85 cents
+        assert_eq!(order.estimate_shipping_cents(850), 1786);
+        let empty = Order::new(OrderId(2), CustomerId(1));
+        assert_eq!(empty.estimate_shipping_cents(850), 0);
+    }
+
+    #[test]
+    fn heavy_orders_do_not_overflow_the_weight() {
+        let mut order = Order::new(OrderId(1), CustomerId(1));
+        order
+            .add_item(OrderItem::new(ProductId(1), u32::MAX, 1).with_weight(1000))
+            .unwrap();
+        order
+            .add_item(OrderItem::new(ProductId(2), u32::MAX, 1).with_weight(1000))
+            .unwrap();
+        assert_eq!(order.total_weight(), 2 * u32::MAX as u64 * 1000);
+        assert_eq!(order.estimate_shipping_cents(1), 2 * u32::MAX as u64);
+    }
 }
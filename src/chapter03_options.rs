@@ -3,7 +3,8 @@
 //! This example demonstrates working with Option and Result types,
 //! showing various patterns for handling absence and errors.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
 
 // A simple user database
 struct UserDatabase {
@@ -15,6 +16,7 @@ struct User {
     id: u64,
     username: String,
     email: Option<String>, // Email is optional
+    version: u64,          // Bumped on every checked update
 }
 
 #[derive(Debug)]
@@ -22,6 +24,7 @@ enum DatabaseError {
     NotFound,
     DuplicateId,
     InvalidData(String),
+    Conflict { expected: u64, actual: u64 },
 }
 
 impl std::fmt::Display for DatabaseError {
@@ -30,6 +33,11 @@ impl std::fmt::Display for DatabaseError {
             DatabaseError::NotFound => write!(f, "User not found"),
             DatabaseError::DuplicateId => write!(f, "User ID already exists"),
             DatabaseError::InvalidData(msg) => write!(f, "Invalid data: {}", msg),
+            DatabaseError::Conflict { expected, actual } => write!(
+                f,
+                "Version conflict: expected {}, found {}",
+                expected, actual
+            ),
         }
     }
 }
@@ -56,6 +64,29 @@ impl UserDatabase {
         Ok(())
     }
 
+    // Optimistic locking: the update only applies if nobody changed the user
+    // since the caller read `expected_version`
+    fn update_user_checked<F>(
+        &mut self,
+        id: u64,
+        expected_version: u64,
+        f: F,
+    ) -> Result<&User, DatabaseError>
+    where
+        F: FnOnce(&mut User),
+    {
+        let user = self.users.get_mut(&id).ok_or(DatabaseError::NotFound)?;
+        if user.version != expected_version {
+            return Err(DatabaseError::Conflict {
+                expected: expected_version,
+                actual: user.version,
+            });
+        }
+        f(user);
+        user.version += 1;
+        Ok(user)
+    }
+
     fn get_user(&self, id: u64) -> Option<&User> {
         self.users.get(&id)
     }
@@ -71,6 +102,137 @@ impl UserDatabase {
     fn get_user_email(&self, id: u64) -> Option<&str> {
         self.get_user(id)?.email.as_deref()
     }
+
+    fn count_by<K, F>(&self, key_fn: F) -> HashMap<K, usize>
+    where
+        K: Eq + Hash,
+        F: Fn(&User) -> K,
+    {
+        let mut counts = HashMap::new();
+        for user in self.users.values() {
+            *counts.entry(key_fn(user)).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    fn count_with_email(&self) -> usize {
+        self.count_by(|u| u.email.is_some())
+            .get(&true)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // Rows are sorted by id so exports are stable; a missing email is an empty cell
+    fn export_csv(&self) -> String {
+        let mut users: Vec<&User> = self.users.values().collect();
+        users.sort_by_key(|u| u.id);
+
+        let mut csv = String::from("id,username,email\n");
+        for user in users {
+            csv.push_str(&format!(
+                "{},{},{}\n",
+                user.id,
+                csv_field(&user.username),
+                csv_field(user.email.as_deref().unwrap_or(""))
+            ));
+        }
+        csv
+    }
+
+    // Validates every row before inserting, so a rejected import adds nothing
+    fn import_csv(&mut self, csv: &str) -> Result<usize, DatabaseError> {
+        let mut rows = parse_csv(csv)?;
+        if rows
+            .first()
+            .is_some_and(|row| row.first().map(String::as_str) == Some("id"))
+        {
+            rows.remove(0);
+        }
+
+        let mut users = Vec::with_capacity(rows.len());
+        let mut seen = HashSet::new();
+        for (i, row) in rows.into_iter().enumerate() {
+            let [id, username, email]: [String; 3] =
+                row.try_into().map_err(|row: Vec<String>| {
+                    DatabaseError::InvalidData(format!(
+                        "row {}: expected 3 fields, got {}",
+                        i + 1,
+                        row.len()
+                    ))
+                })?;
+            let id = id.parse().map_err(|_| {
+                DatabaseError::InvalidData(format!("row {}: bad id '{}'", i + 1, id))
+            })?;
+            if username.is_empty() {
+                return Err(DatabaseError::InvalidData(format!(
+                    "row {}: username cannot be empty",
+                    i + 1
+                )));
+            }
+            if self.users.contains_key(&id) || !seen.insert(id) {
+                return Err(DatabaseError::DuplicateId);
+            }
+            users.push(User {
+                id,
+                username,
+                email: (!email.is_empty()).then_some(email),
+                version: 0,
+            });
+        }
+
+        let count = users.len();
+        for user in users {
+            self.users.insert(user.id, user);
+        }
+        Ok(count)
+    }
+}
+
+// Quote a field only when needed; embedded quotes are doubled
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Minimal RFC 4180 reader: quoted fields may contain commas, quotes and newlines
+fn parse_csv(input: &str) -> Result<Vec<Vec<String>>, DatabaseError> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if field.is_empty() => in_quotes = true,
+            (',', false) => row.push(std::mem::take(&mut field)),
+            ('\n', false) => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            ('\r', false) => {}
+            (c, _) => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(DatabaseError::InvalidData(
+            "unterminated quoted field".to_string(),
+        ));
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
 }
 
 fn demonstrate_option_combinators() {
@@ -153,16 +315,19 @@ fn main() {
             id: 1,
             username: "alice".to_string(),
             email: Some("alice@example.com".to_string()),
+            version: 0,
         },
         User {
             id: 2,
             username: "bob".to_string(),
             email: None,
+            version: 0,
         },
         User {
             id: 3,
             username: "charlie".to_string(),
             email: Some("charlie@example.com".to_string()),
+            version: 0,
         },
     ];
 
@@ -178,6 +343,7 @@ fn main() {
         id: 1,
         username: "duplicate".to_string(),
         email: None,
+        version: 0,
     };
     match db.add_user(duplicate) {
         Ok(()) => println!("Added user"),
@@ -220,4 +386,101 @@ fn main() {
         Ok(email) => println!("User 999 email via ?: {}", email),
         Err(e) => println!("Error: {}", e),
     }
+
+    println!("\n--- Aggregation ---");
+    let by_email = db.count_by(|u| u.email.is_some());
+    println!("With email: {:?}", by_email.get(&true));
+    println!("Without email: {:?}", by_email.get(&false));
+    println!("count_with_email(): {}", db.count_with_email());
+
+    println!("\n--- CSV round trip ---");
+    db.add_user(User {
+        id: 4,
+        username: "doe, \"jd\" john".to_string(),
+        email: None,
+        version: 0,
+    })
+    .unwrap();
+    let exported = db.export_csv();
+    print!("{}", exported);
+
+    let mut copy = UserDatabase::new();
+    match copy.import_csv(&exported) {
+        Ok(count) => println!("Imported {} users", count),
+        Err(e) => println!("Import failed: {}", e),
+    }
+    println!("Round trip identical: {}", copy.export_csv() == exported);
+    match copy.import_csv("id,username,email\n5,\"broken") {
+        Ok(count) => println!("Imported {} users", count),
+        Err(e) => println!("Malformed import rejected: {}", e),
+    }
+    match copy.import_csv("id,username,email\n5,eve,\n1,mallory,") {
+        Ok(count) => println!("Imported {} users", count),
+        Err(e) => println!("Import with a taken id rejected: {}", e),
+    }
+    println!("User 5 after rejected import: {:?}", copy.get_user(5));
+
+    println!("\n--- Optimistic locking ---");
+    // Two editors read bob at the same version
+    let seen = db.get_user(2).map(|u| u.version).unwrap_or_default();
+    match db.update_user_checked(2, seen, |u| u.email = Some("bob@example.com".to_string())) {
+        Ok(user) => println!("First update applied: {:?}", user),
+        Err(e) => println!("First update failed: {}", e),
+    }
+    match db.update_user_checked(2, seen, |u| u.username = "robert".to_string()) {
+        Ok(user) => println!("Stale update applied: {:?}", user),
+        Err(e) => println!("Stale update rejected: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: u64, username: &str) -> User {
+        User {
+            id,
+            username: username.to_string(),
+            email: None,
+            version: 0,
+        }
+    }
+
+    #[test]
+    fn import_round_trips_export() {
+        let mut db = UserDatabase::new();
+        db.add_user(user(1, "alice")).unwrap();
+        db.add_user(user(2, "doe, \"jd\" john")).unwrap();
+
+        let mut copy = UserDatabase::new();
+        assert_eq!(copy.import_csv(&db.export_csv()).unwrap(), 2);
+        assert_eq!(copy.export_csv(), db.export_csv());
+    }
+
+    #[test]
+    fn import_with_existing_id_adds_nothing() {
+        let mut db = UserDatabase::new();
+        db.add_user(user(1, "alice")).unwrap();
+
+        let result = db.import_csv("id,username,email\n2,bob,\n1,mallory,");
+        assert!(matches!(result, Err(DatabaseError::DuplicateId)));
+        assert!(db.get_user(2).is_none());
+        assert_eq!(db.get_user(1).unwrap().username, "alice");
+    }
+
+    #[test]
+    fn import_with_repeated_id_adds_nothing() {
+        let mut db = UserDatabase::new();
+        let result = db.import_csv("2,bob,\n3,carol,\n2,bobby,");
+        assert!(matches!(result, Err(DatabaseError::DuplicateId)));
+        assert!(db.users.is_empty());
+    }
+
+    #[test]
+    fn import_with_empty_username_adds_nothing() {
+        let mut db = UserDatabase::new();
+        let result = db.import_csv("2,bob,\n3,,carol@example.com");
+        assert!(matches!(result, Err(DatabaseError::InvalidData(_))));
+        assert!(db.users.is_empty());
+    }
 }
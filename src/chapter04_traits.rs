@@ -3,6 +3,8 @@
 //! This example demonstrates traits, default implementations,
 //! trait bounds, and static vs dynamic dispatch.
 
+use std::cell::{Cell, OnceCell};
+use std::collections::HashSet;
 use std::fmt::Display;
 
 trait Summary {
@@ -79,6 +81,56 @@ fn create_feed() -> Vec<Box<dyn Summary>> {
     ]
 }
 
+// Drops items whose summary matches one already kept; first occurrence wins
+fn dedupe_feed(feed: Vec<Box<dyn Summary>>) -> Vec<Box<dyn Summary>> {
+    let mut seen = HashSet::new();
+    feed.into_iter()
+        .filter(|item| seen.insert(item.summarize()))
+        .collect()
+}
+
+// Caching wrapper: computes the summary once, then serves the stored copy
+struct CachedSummary<T: Summary> {
+    inner: T,
+    cached: OnceCell<String>,
+}
+
+impl<T: Summary> CachedSummary<T> {
+    fn new(inner: T) -> Self {
+        Self {
+            inner,
+            cached: OnceCell::new(),
+        }
+    }
+}
+
+impl<T: Summary> Summary for CachedSummary<T> {
+    fn summarize_author(&self) -> String {
+        self.inner.summarize_author()
+    }
+
+    fn summarize(&self) -> String {
+        self.cached.get_or_init(|| self.inner.summarize()).clone()
+    }
+}
+
+// Stands in for a summary that is expensive to produce
+struct Report {
+    author: String,
+    summarize_calls: Cell<u32>,
+}
+
+impl Summary for Report {
+    fn summarize_author(&self) -> String {
+        self.author.clone()
+    }
+
+    fn summarize(&self) -> String {
+        self.summarize_calls.set(self.summarize_calls.get() + 1);
+        format!("Quarterly report by {}", self.author)
+    }
+}
+
 // Supertraits
 trait OutlinePrint: Display {
     fn outline_print(&self) {
@@ -98,6 +150,12 @@ trait Container {
     fn add(&mut self, item: Self::Item);
     fn get(&self, index: usize) -> Option<&Self::Item>;
     fn len(&self) -> usize;
+
+    fn extend(&mut self, items: impl IntoIterator<Item = Self::Item>) {
+        for item in items {
+            self.add(item);
+        }
+    }
 }
 
 struct VecContainer<T> {
@@ -108,6 +166,10 @@ impl<T> VecContainer<T> {
     fn new() -> Self {
         Self { items: Vec::new() }
     }
+
+    fn retain(&mut self, pred: impl Fn(&T) -> bool) {
+        self.items.retain(pred);
+    }
 }
 
 impl<T> Container for VecContainer<T> {
@@ -160,6 +222,39 @@ fn main() {
         println!("  {}: {}", i + 1, item.summarize());
     }
 
+    println!("\n=== Deduplicated Feed ===\n");
+    let mut feed = create_feed();
+    // A syndicated copy of the article and a second tweet from the same account
+    feed.push(Box::new(NewsArticle {
+        headline: "Rust 2024 Edition Released".to_string(),
+        location: "San Francisco".to_string(),
+        author: "Jane Developer".to_string(),
+        content: "Syndicated: The Rust team has announced...".to_string(),
+    }));
+    feed.push(Box::new(Tweet {
+        username: "rustlang".to_string(),
+        content: "Have you tried the new edition?".to_string(),
+    }));
+    let before = feed.len();
+    let deduped = dedupe_feed(feed);
+    for (i, item) in deduped.iter().enumerate() {
+        println!("  {}: {}", i + 1, item.summarize());
+    }
+    println!("Removed {} duplicate(s)", before - deduped.len());
+
+    println!("\n=== Caching Wrapper ===\n");
+    let cached = CachedSummary::new(Report {
+        author: "Finance Team".to_string(),
+        summarize_calls: Cell::new(0),
+    });
+    for _ in 0..3 {
+        println!("  {}", cached.summarize());
+    }
+    println!(
+        "Underlying summarize() ran {} time(s)",
+        cached.inner.summarize_calls.get()
+    );
+
     println!("\n=== Supertraits ===\n");
     tweet.outline_print();
 
@@ -171,4 +266,13 @@ fn main() {
     if let Some(item) = container.get(1) {
         println!("Item at index 1: {}", item);
     }
+
+    let mut numbers: VecContainer<u32> = VecContainer::new();
+    numbers.extend(1..=10);
+    println!("Extended with 1..=10: {} items", numbers.len());
+    numbers.retain(|n| n % 2 == 0);
+    let evens: Vec<u32> = (0..numbers.len())
+        .filter_map(|i| numbers.get(i).copied())
+        .collect();
+    println!("Retained evens: {:?}", evens);
 }
@@ -2,8 +2,10 @@
 //!
 //! Custom error types, the ? operator, thiserror, and anyhow.
 
+use std::fmt;
 use std::fs;
 use std::io;
+use std::str::FromStr;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -24,12 +26,61 @@ enum ConfigError {
     Io(#[from] io::Error),
 }
 
+// A port that is known to be usable: 0 is rejected at construction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Port(u16);
+
+impl Port {
+    fn try_new(value: u16) -> Result<Self, ConfigError> {
+        if value == 0 {
+            return Err(ConfigError::InvalidValue {
+                field: "port".to_string(),
+                message: "port 0 is not a usable port".to_string(),
+            });
+        }
+        Ok(Port(value))
+    }
+
+    fn get(self) -> u16 {
+        self.0
+    }
+}
+
+impl FromStr for Port {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.parse().map_err(|_| ConfigError::InvalidValue {
+            field: "port".to_string(),
+            message: format!("'{}' is not a valid port number", s),
+        })?;
+        Port::try_new(value)
+    }
+}
+
+impl fmt::Display for Port {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 #[derive(Debug)]
 struct Config {
     host: String,
-    port: u16,
+    port: Port,
     max_connections: u32,
     timeout_seconds: u64,
+    allowed_hosts: Vec<String>,
+}
+
+// Comma-separated values; blank entries (e.g. from a trailing comma) are dropped
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
 }
 
 fn parse_config(content: &str) -> Result<Config, ConfigError> {
@@ -37,6 +88,7 @@ fn parse_config(content: &str) -> Result<Config, ConfigError> {
     let mut port = None;
     let mut max_connections = None;
     let mut timeout_seconds = None;
+    let mut allowed_hosts = Vec::new();
 
     for line in content.lines() {
         let line = line.trim();
@@ -57,12 +109,7 @@ fn parse_config(content: &str) -> Result<Config, ConfigError> {
 
         match key {
             "host" => host = Some(value.to_string()),
-            "port" => {
-                port = Some(value.parse().map_err(|_| ConfigError::InvalidValue {
-                    field: "port".to_string(),
-                    message: format!("'{}' is not a valid port number", value),
-                })?)
-            }
+            "port" => port = Some(value.parse::<Port>()?),
             "max_connections" => {
                 max_connections = Some(value.parse().map_err(|_| ConfigError::InvalidValue {
                     field: "max_connections".to_string(),
@@ -75,6 +122,7 @@ fn parse_config(content: &str) -> Result<Config, ConfigError> {
                     message: format!("'{}' is not a valid number", value),
                 })?)
             }
+            "allowed_hosts" => allowed_hosts = parse_list(value),
             _ => {}
         }
     }
@@ -86,6 +134,7 @@ fn parse_config(content: &str) -> Result<Config, ConfigError> {
             .ok_or(ConfigError::MissingField("max_connections".to_string()))?,
         timeout_seconds: timeout_seconds
             .ok_or(ConfigError::MissingField("timeout".to_string()))?,
+        allowed_hosts,
     })
 }
 
@@ -111,10 +160,12 @@ fn run_application() -> anyhow::Result<()> {
         port = 8080
         max_connections = 100
         timeout = 30
+        allowed_hosts = example.com, api.example.com,
     "#;
 
     let config = parse_config(config_content).context("Failed to parse configuration")?;
     println!("Loaded configuration: {:?}", config);
+    println!("Allowed hosts: {:?}", config.allowed_hosts);
 
     validate_config(&config).context("Configuration validation failed")?;
     println!("Configuration is valid!");
@@ -124,9 +175,6 @@ fn run_application() -> anyhow::Result<()> {
 fn validate_config(config: &Config) -> anyhow::Result<()> {
     use anyhow::bail;
 
-    if config.port == 0 {
-        bail!("Port cannot be zero");
-    }
     if config.max_connections == 0 {
         bail!("max_connections must be greater than zero");
     }
@@ -136,6 +184,78 @@ fn validate_config(config: &Config) -> anyhow::Result<()> {
     Ok(())
 }
 
+type Rule = Box<dyn Fn(&Config) -> Result<(), String>>;
+
+// Runs every rule and reports all failures, not just the first
+struct Validator {
+    rules: Vec<(String, Rule)>,
+}
+
+impl Validator {
+    fn new() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    fn rule<F>(mut self, name: &str, check: F) -> Self
+    where
+        F: Fn(&Config) -> Result<(), String> + 'static,
+    {
+        self.rules.push((name.to_string(), Box::new(check)));
+        self
+    }
+
+    fn validate(&self, config: &Config) -> Result<(), Vec<String>> {
+        let failures: Vec<String> = self
+            .rules
+            .iter()
+            .filter_map(|(name, check)| check(config).err().map(|e| format!("{}: {}", name, e)))
+            .collect();
+        if failures.is_empty() { Ok(()) } else { Err(failures) }
+    }
+}
+
+fn demonstrate_validator() -> Result<(), ConfigError> {
+    let validator = Validator::new()
+        .rule("host", |c| {
+            if c.host.contains(' ') {
+                Err(format!("'{}' contains whitespace", c.host))
+            } else {
+                Ok(())
+            }
+        })
+        .rule("privileged_port", |c| {
+            if c.port.get() < 1024 {
+                Err(format!("port {} requires root", c.port))
+            } else {
+                Ok(())
+            }
+        })
+        .rule("self_allowed", |c| {
+            if c.allowed_hosts.is_empty() || c.allowed_hosts.contains(&c.host) {
+                Ok(())
+            } else {
+                Err(format!("{} is not in allowed_hosts", c.host))
+            }
+        });
+
+    let good = parse_config("host = example.com\nport = 8080\nmax_connections = 10\ntimeout = 5")?;
+    println!("Good config: {:?}", validator.validate(&good));
+
+    let bad = parse_config(
+        "host = internal.local\nport = 80\nmax_connections = 10\ntimeout = 5\nallowed_hosts = example.com",
+    )?;
+    match validator.validate(&bad) {
+        Ok(()) => println!("Bad config unexpectedly passed"),
+        Err(failures) => {
+            println!("Bad config failed {} rules:", failures.len());
+            for failure in failures {
+                println!("  - {}", failure);
+            }
+        }
+    }
+    Ok(())
+}
+
 fn main() {
     println!("=== Error Handling Patterns ===\n");
 
@@ -145,6 +265,15 @@ fn main() {
         Err(e) => println!("Error (expected): {}", e),
     }
 
+    println!("\n=== Validated Port Newtype ===\n");
+    println!("Port::try_new(8080): {:?}", Port::try_new(8080).map(Port::get));
+    for input in ["0", "70000"] {
+        match input.parse::<Port>() {
+            Ok(port) => println!("Parsed port {}", port),
+            Err(e) => println!("Parsing {:?} failed: {}", input, e),
+        }
+    }
+
     println!("\n=== Loading Config from File ===\n");
     match load_config("/nonexistent/config.txt") {
         Ok(config) => println!("Loaded: {:?}", config),
@@ -155,4 +284,9 @@ fn main() {
     if let Err(e) = run_application() {
         println!("Application error: {}", e);
     }
+
+    println!("\n=== Validating with Custom Rules ===\n");
+    if let Err(e) = demonstrate_validator() {
+        println!("Validator demo error: {}", e);
+    }
 }
@@ -1,6 +1,7 @@
 //! Chapter 9: Creational Patterns - Builder Pattern
 
 use std::marker::PhantomData;
+use std::str::FromStr;
 use std::time::Duration;
 
 // Standard Builder
@@ -13,13 +14,81 @@ struct HttpRequest {
     timeout: Duration,
 }
 
-#[derive(Default)]
+impl HttpRequest {
+    // Header names are case-insensitive in HTTP
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(h, _)| h.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn headers_all(&self, name: &str) -> Vec<&str> {
+        self.headers
+            .iter()
+            .filter(|(h, _)| h.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+}
+
+#[derive(Default, Clone)]
 struct HttpRequestBuilder {
     method: Option<String>,
     url: Option<String>,
     headers: Vec<(String, String)>,
     body: Option<String>,
     timeout: Option<Duration>,
+    preset_headers: Vec<String>,
+}
+
+// Shared defaults loaded from a config string
+#[derive(Debug, Clone)]
+struct RequestPreset {
+    base_url: String,
+    headers: Vec<(String, String)>,
+    timeout: Option<Duration>,
+}
+
+impl RequestPreset {
+    // Lines are `key = value`; headers use `header.Name = value`
+    fn parse(config: &str) -> Result<Self, String> {
+        let mut base_url = None;
+        let mut headers = Vec::new();
+        let mut timeout = None;
+
+        for line in config.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("Expected key=value, got: {}", line))?;
+            let (key, value) = (key.trim(), value.trim());
+
+            match key {
+                "base_url" => base_url = Some(value.to_string()),
+                "timeout" => {
+                    let secs = value
+                        .parse()
+                        .map_err(|_| format!("'{}' is not a valid timeout", value))?;
+                    timeout = Some(Duration::from_secs(secs));
+                }
+                _ => match key.strip_prefix("header.") {
+                    Some(name) => headers.push((name.to_string(), value.to_string())),
+                    None => return Err(format!("Unknown preset key: {}", key)),
+                },
+            }
+        }
+
+        Ok(Self {
+            base_url: base_url.ok_or("base_url is required")?,
+            headers,
+            timeout,
+        })
+    }
 }
 
 impl HttpRequestBuilder {
@@ -27,6 +96,27 @@ impl HttpRequestBuilder {
         Self::default()
     }
 
+    fn from_preset(preset: &RequestPreset) -> Self {
+        Self {
+            url: Some(preset.base_url.clone()),
+            headers: preset.headers.clone(),
+            timeout: preset.timeout,
+            preset_headers: preset.headers.iter().map(|(name, _)| name.clone()).collect(),
+            ..Self::default()
+        }
+    }
+
+    // Fork a preconfigured base without giving it up
+    fn template(&self) -> Self {
+        self.clone()
+    }
+
+    // Appends to the current URL, e.g. a preset's base_url
+    fn path(mut self, path: &str) -> Self {
+        self.url = Some(format!("{}{}", self.url.unwrap_or_default(), path));
+        self
+    }
+
     fn method(mut self, method: &str) -> Self {
         self.method = Some(method.to_string());
         self
@@ -38,6 +128,16 @@ impl HttpRequestBuilder {
     }
 
     fn header(mut self, name: &str, value: &str) -> Self {
+        // An explicit header replaces a preset default of the same name
+        if self
+            .preset_headers
+            .iter()
+            .any(|h| h.eq_ignore_ascii_case(name))
+        {
+            self.preset_headers
+                .retain(|h| !h.eq_ignore_ascii_case(name));
+            self.headers.retain(|(h, _)| !h.eq_ignore_ascii_case(name));
+        }
         self.headers.push((name.to_string(), value.to_string()));
         self
     }
@@ -63,6 +163,31 @@ impl HttpRequestBuilder {
     }
 }
 
+// Port 0 means "pick any" to the OS, never a real server port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Port(u16);
+
+impl Port {
+    fn try_new(value: u16) -> Result<Self, String> {
+        if value == 0 {
+            Err("Port must be non-zero".to_string())
+        } else {
+            Ok(Port(value))
+        }
+    }
+}
+
+impl FromStr for Port {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s
+            .parse::<u16>()
+            .map_err(|_| format!("'{}' is not a valid port", s))?;
+        Port::try_new(value)
+    }
+}
+
 // Typestate Builder
 mod typestate {
     use super::*;
@@ -75,14 +200,14 @@ mod typestate {
     #[derive(Debug)]
     pub struct Connection {
         pub host: String,
-        pub port: u16,
+        pub port: Port,
         pub use_tls: bool,
         pub pool_size: u32,
     }
 
     pub struct ConnectionBuilder<H, P> {
         host: Option<String>,
-        port: Option<u16>,
+        port: Option<Port>,
         use_tls: bool,
         pool_size: u32,
         _host_state: PhantomData<H>,
@@ -122,7 +247,7 @@ mod typestate {
     }
 
     impl<H> ConnectionBuilder<H, NoPort> {
-        pub fn port(self, port: u16) -> ConnectionBuilder<H, HasPort> {
+        pub fn port(self, port: Port) -> ConnectionBuilder<H, HasPort> {
             ConnectionBuilder {
                 host: self.host,
                 port: Some(port),
@@ -146,6 +271,20 @@ mod typestate {
         }
     }
 
+    impl Connection {
+        // Host and port are already known, so the builder skips straight to the complete state
+        pub fn into_builder(self) -> ConnectionBuilder<HasHost, HasPort> {
+            ConnectionBuilder {
+                host: Some(self.host),
+                port: Some(self.port),
+                use_tls: self.use_tls,
+                pool_size: self.pool_size,
+                _host_state: PhantomData,
+                _port_state: PhantomData,
+            }
+        }
+    }
+
     impl ConnectionBuilder<HasHost, HasPort> {
         pub fn build(self) -> Connection {
             Connection {
@@ -174,19 +313,125 @@ fn main() {
     let invalid = HttpRequestBuilder::new().url("https://example.com").build();
     println!("Invalid request (no method): {:?}", invalid);
 
+    println!("\n=== Builder from Preset ===\n");
+
+    let preset = RequestPreset::parse(
+        r#"
+        base_url = https://api.example.com
+        timeout = 5
+        header.Accept = application/json
+        header.User-Agent = oop-to-rust/1.0
+    "#,
+    )
+    .expect("Failed to parse preset");
+
+    let request = HttpRequestBuilder::from_preset(&preset)
+        .method("POST")
+        .path("/users")
+        .header("accept", "application/xml")
+        .body(r#"{"name": "ferris"}"#)
+        .timeout(Duration::from_secs(60))
+        .build()
+        .expect("Failed to build request");
+
+    println!("{} {}", request.method, request.url);
+    println!("Timeout: {:?}", request.timeout);
+    for (name, value) in &request.headers {
+        println!("Header: {}: {}", name, value);
+    }
+    println!("Body: {:?}", request.body);
+
+    println!("\n=== Header Lookup ===\n");
+
+    let request = HttpRequestBuilder::new()
+        .method("GET")
+        .url("https://api.example.com/feed")
+        .header("Accept", "application/json")
+        .header("Cache-Control", "no-cache")
+        .header("cache-control", "max-age=0")
+        .build()
+        .expect("Failed to build request");
+
+    println!("header(\"ACCEPT\"): {:?}", request.header("ACCEPT"));
+    println!("header(\"X-Trace-Id\"): {:?}", request.header("X-Trace-Id"));
+    println!(
+        "headers_all(\"Cache-Control\"): {:?}",
+        request.headers_all("Cache-Control")
+    );
+
+    println!("\n=== Request Templates ===\n");
+
+    let base = HttpRequestBuilder::new()
+        .method("GET")
+        .header("Authorization", "Bearer token123")
+        .header("Accept", "application/json");
+
+    let users = base
+        .template()
+        .url("https://api.example.com/users")
+        .build()
+        .expect("Failed to build request");
+    let orders = base
+        .template()
+        .url("https://api.example.com/orders")
+        .build()
+        .expect("Failed to build request");
+
+    for request in [&users, &orders] {
+        println!("{} {:?}", request.url, request.headers);
+        assert_eq!(request.header("Authorization"), Some("Bearer token123"));
+        assert_eq!(request.header("Accept"), Some("application/json"));
+    }
+
     println!("\n=== Typestate Builder Pattern ===\n");
 
     let connection = typestate::ConnectionBuilder::new()
         .host("localhost")
-        .port(5432)
+        .port(Port::try_new(5432).expect("5432 is a valid port"))
         .use_tls(true)
         .pool_size(20)
         .build();
 
     println!("Connection: {:?}", connection);
 
+    let resized = connection.into_builder().pool_size(50).build();
+    println!("Reconfigured: {:?}", resized);
+
+    println!("Port::try_new(0): {:?}", Port::try_new(0));
+    println!("\"8080\".parse(): {:?}", "8080".parse::<Port>());
+    println!("\"70000\".parse(): {:?}", "70000".parse::<Port>());
+
     // The following would NOT compile:
     // let invalid = typestate::ConnectionBuilder::new()
     //     .host("localhost")
     //     .build();  // Error: build() not available without port
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explicit_headers_replace_repeated_preset_header() {
+        let preset = RequestPreset::parse(
+            "base_url = https://api.example.com\n\
+             header.Accept = text/html\n\
+             header.accept = text/plain\n\
+             header.User-Agent = preset",
+        )
+        .unwrap();
+
+        let request = HttpRequestBuilder::from_preset(&preset)
+            .method("GET")
+            .header("ACCEPT", "application/json")
+            .header("Accept", "application/xml")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            request.headers_all("accept"),
+            ["application/json", "application/xml"]
+        );
+        assert_eq!(request.header("User-Agent"), Some("preset"));
+    }
+}
@@ -115,6 +115,7 @@ fn get_ui_factory(platform: &str) -> Box<dyn UiFactory> {
 enum Shape {
     Circle { radius: f64 },
     Rectangle { width: f64, height: f64 },
+    Triangle { base: f64, height: f64 },
 }
 
 impl Shape {
@@ -126,12 +127,34 @@ impl Shape {
         Shape::Rectangle { width, height }
     }
 
+    fn triangle(base: f64, height: f64) -> Self {
+        Shape::Triangle { base, height }
+    }
+
     fn area(&self) -> f64 {
         match self {
             Shape::Circle { radius } => std::f64::consts::PI * radius * radius,
             Shape::Rectangle { width, height } => width * height,
+            Shape::Triangle { base, height } => 0.5 * base * height,
         }
     }
+
+    // Axis-aligned bounding box as (width, height)
+    fn bounding_box(&self) -> (f64, f64) {
+        match self {
+            Shape::Circle { radius } => (2.0 * radius, 2.0 * radius),
+            Shape::Rectangle { width, height } => (*width, *height),
+            Shape::Triangle { base, height } => (*base, *height),
+        }
+    }
+}
+
+// Smallest box that fits any one of the shapes
+fn total_bounds(shapes: &[Shape]) -> (f64, f64) {
+    shapes
+        .iter()
+        .map(Shape::bounding_box)
+        .fold((0.0, 0.0), |(w, h), (sw, sh)| (w.max(sw), h.max(sh)))
 }
 
 fn main() {
@@ -155,9 +178,20 @@ fn main() {
 
     println!("\n=== Enum-Based Factory ===\n");
 
-    let shapes = vec![Shape::circle(5.0), Shape::rectangle(4.0, 6.0)];
+    let shapes = vec![
+        Shape::circle(5.0),
+        Shape::rectangle(4.0, 6.0),
+        Shape::triangle(12.0, 3.0),
+    ];
 
     for shape in &shapes {
-        println!("{:?} - Area: {:.2}", shape, shape.area());
+        println!(
+            "{:?} - Area: {:.2}, Bounds: {:?}",
+            shape,
+            shape.area(),
+            shape.bounding_box()
+        );
     }
+
+    println!("Total bounds: {:?}", total_bounds(&shapes));
 }
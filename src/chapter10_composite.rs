@@ -1,8 +1,16 @@
 //! Chapter 10: Structural Patterns - Composite Pattern
 
+use std::collections::hash_map::DefaultHasher;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WalkAction {
+    Continue,
+    SkipChildren,
+}
+
+#[derive(Clone, PartialEq, Hash)]
 enum FileEntry {
     File { name: String, size: u64 },
     Directory { name: String, children: Vec<FileEntry> },
@@ -46,6 +54,135 @@ impl FileEntry {
         }
     }
 
+    fn collect_files<'a>(&'a self, out: &mut Vec<(&'a str, u64)>) {
+        match self {
+            FileEntry::File { name, size } => out.push((name, *size)),
+            FileEntry::Directory { children, .. } => {
+                for child in children {
+                    child.collect_files(out);
+                }
+            }
+        }
+    }
+
+    // Biggest first; equal sizes are ordered by name
+    fn largest_files(&self, n: usize) -> Vec<(&str, u64)> {
+        let mut files = Vec::new();
+        self.collect_files(&mut files);
+        files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        files.truncate(n);
+        files
+    }
+
+    // Paths are slash-joined from this entry down, e.g. "my-project/src/lib.rs"
+    fn collect_paths(&self, prefix: &str, out: &mut Vec<(String, u64)>) {
+        let path = if prefix.is_empty() {
+            self.name().to_string()
+        } else {
+            format!("{}/{}", prefix, self.name())
+        };
+        match self {
+            FileEntry::File { size, .. } => out.push((path, *size)),
+            FileEntry::Directory { children, .. } => {
+                for child in children {
+                    child.collect_paths(&path, out);
+                }
+            }
+        }
+    }
+
+    // Reports every oversized file plus the total, instead of stopping at the first problem
+    fn validate_limits(&self, max_file: u64, max_total: u64) -> Result<(), Vec<String>> {
+        let mut files = Vec::new();
+        self.collect_paths("", &mut files);
+
+        let mut problems: Vec<String> = files
+            .iter()
+            .filter(|(_, size)| *size > max_file)
+            .map(|(path, size)| {
+                format!("{} is {} (limit {})", path, format_size(*size), format_size(max_file))
+            })
+            .collect();
+
+        let total = self.size();
+        if total > max_total {
+            problems.push(format!(
+                "total {} exceeds cap {}",
+                format_size(total),
+                format_size(max_total)
+            ));
+        }
+
+        if problems.is_empty() { Ok(()) } else { Err(problems) }
+    }
+
+    // Overlay `other` on top of `self`: directories merge recursively, and
+    // anything else with the same name (file/file or file/dir) is replaced by `other`
+    fn merge(self, other: FileEntry) -> FileEntry {
+        match (self, other) {
+            (
+                FileEntry::Directory { name, mut children },
+                FileEntry::Directory {
+                    children: overlay, ..
+                },
+            ) => {
+                for entry in overlay {
+                    match children.iter().position(|c| c.name() == entry.name()) {
+                        Some(i) => {
+                            let existing = children.remove(i);
+                            children.insert(i, existing.merge(entry));
+                        }
+                        None => children.push(entry),
+                    }
+                }
+                FileEntry::Directory { name, children }
+            }
+            (_, other) => other,
+        }
+    }
+
+    // Derived Hash covers variant, names, sizes and child order, so any change shows up.
+    // DefaultHasher is deterministic, but not guaranteed stable across Rust releases.
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // Pre-order traversal; the visitor decides whether to descend
+    fn walk(&self, visit: &mut dyn FnMut(&FileEntry, usize) -> WalkAction) {
+        self.walk_at(0, visit);
+    }
+
+    fn walk_at(&self, depth: usize, visit: &mut dyn FnMut(&FileEntry, usize) -> WalkAction) {
+        if visit(self, depth) == WalkAction::SkipChildren {
+            return;
+        }
+        if let FileEntry::Directory { children, .. } = self {
+            for child in children {
+                child.walk_at(depth + 1, visit);
+            }
+        }
+    }
+
+    // GitHub-flavored nested list: bold directories, files with their size
+    fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        self.walk(&mut |entry, depth| {
+            let indent = "  ".repeat(depth);
+            match entry {
+                FileEntry::File { name, size } => {
+                    out.push_str(&format!("{}- {} ({})\n", indent, name, format_size(*size)))
+                }
+                FileEntry::Directory { name, .. } => {
+                    out.push_str(&format!("{}- **{}/**\n", indent, name))
+                }
+            }
+            WalkAction::Continue
+        });
+        out
+    }
+
     fn print_tree(&self, prefix: &str, is_last: bool) {
         let connector = if is_last { "└── " } else { "├── " };
         let icon = match self {
@@ -75,6 +212,41 @@ impl fmt::Debug for FileEntry {
     }
 }
 
+// Fluent builder: reads top-down instead of inside-out
+struct DirBuilder {
+    name: String,
+    children: Vec<FileEntry>,
+}
+
+impl DirBuilder {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            children: Vec::new(),
+        }
+    }
+
+    fn file(mut self, name: &str, size: u64) -> Self {
+        self.children.push(FileEntry::file(name, size));
+        self
+    }
+
+    fn dir<F>(mut self, name: &str, f: F) -> Self
+    where
+        F: FnOnce(DirBuilder) -> DirBuilder,
+    {
+        self.children.push(f(DirBuilder::new(name)).build());
+        self
+    }
+
+    fn build(self) -> FileEntry {
+        FileEntry::Directory {
+            name: self.name,
+            children: self.children,
+        }
+    }
+}
+
 fn format_size(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
@@ -121,4 +293,108 @@ fn main() {
     println!("\n=== Statistics ===\n");
     println!("Total size: {}", format_size(project.size()));
     println!("Total files: {}", project.count_files());
+
+    println!("\n=== Upload Limits ===\n");
+    let limits = [
+        (16 * 1024, 64 * 1024),
+        (4 * 1024, 64 * 1024),
+        (16 * 1024, 16 * 1024),
+    ];
+    for (max_file, max_total) in limits {
+        println!(
+            "max file {}, max total {}:",
+            format_size(max_file),
+            format_size(max_total)
+        );
+        match project.validate_limits(max_file, max_total) {
+            Ok(()) => println!("  OK"),
+            Err(problems) => {
+                for problem in problems {
+                    println!("  - {}", problem);
+                }
+            }
+        }
+    }
+
+    println!("\n=== Largest Files ===\n");
+    for (name, size) in project.largest_files(3) {
+        println!("{:>10}  {}", format_size(size), name);
+    }
+    println!(
+        "Asking for 100 returns all {} files",
+        project.largest_files(100).len()
+    );
+
+    println!("\n=== Fingerprints ===\n");
+    let copy = project.clone();
+    let mut resized = project.clone();
+    if let FileEntry::Directory { children, .. } = &mut resized {
+        children[0] = FileEntry::file("Cargo.toml", 600);
+    }
+    println!("Original: {:016x}", project.fingerprint());
+    println!("Clone:    {:016x}", copy.fingerprint());
+    println!("Resized:  {:016x}", resized.fingerprint());
+
+    println!("\n=== Markdown ===\n");
+    let markdown = project.to_markdown();
+    print!("{}", markdown);
+    let expected = "\
+- **my-project/**
+  - Cargo.toml (512 bytes)
+  - README.md (2.00 KB)
+  - **src/**
+    - main.rs (1.00 KB)
+    - lib.rs (4.00 KB)
+    - **models/**
+      - mod.rs (256 bytes)
+      - user.rs (2.00 KB)
+  - **tests/**
+    - integration_test.rs (8.00 KB)
+";
+    println!("Matches expected: {}", markdown == expected);
+
+    println!("\n=== Walking with Pruning ===\n");
+    project.walk(&mut |entry, depth| {
+        if entry.name() == "tests" {
+            println!("{}{} (skipped)", "  ".repeat(depth), entry.name());
+            return WalkAction::SkipChildren;
+        }
+        println!("{}{}", "  ".repeat(depth), entry.name());
+        WalkAction::Continue
+    });
+
+    println!("\n=== Fluent Builder ===\n");
+    let built = DirBuilder::new("my-project")
+        .file("Cargo.toml", 512)
+        .file("README.md", 2048)
+        .dir("src", |src| {
+            src.file("main.rs", 1024)
+                .file("lib.rs", 4096)
+                .dir("models", |models| {
+                    models.file("mod.rs", 256).file("user.rs", 2048)
+                })
+        })
+        .dir("tests", |tests| tests.file("integration_test.rs", 8192))
+        .build();
+
+    built.print_tree("", true);
+    println!("\nSame structure as hand-written tree: {}", built == project);
+
+    println!("\n=== Overlay Merge ===\n");
+    let overlay = DirBuilder::new("overlay")
+        .file("README.md", 4096)
+        .dir("src", |src| {
+            src.file("lib.rs", 5120)
+                .dir("models", |models| models.file("order.rs", 1536))
+        })
+        .dir("tests", |tests| tests.file("smoke_test.rs", 1024))
+        .dir("Cargo.toml", |d| d.file("conflict.txt", 1))
+        .build();
+
+    let merged = built.merge(overlay);
+    merged.print_tree("", true);
+    println!("\nMerged files: {}, total size: {}", merged.count_files(), format_size(merged.size()));
+    for (name, size) in merged.largest_files(3) {
+        println!("{:>10}  {}", format_size(size), name);
+    }
 }
@@ -1,6 +1,11 @@
 //! Chapter 10: Structural Patterns - Decorator Pattern
 
-use std::time::{Duration, Instant};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Notify;
 
 trait Notifier: Send + Sync {
     fn send(&self, message: &str) -> Result<(), String>;
@@ -29,19 +34,39 @@ impl Notifier for EmailNotifier {
     }
 }
 
+// Formatting strategy for log lines: (notifier name, message) -> line
+type LogFormat = Box<dyn Fn(&str, &str) -> String + Send + Sync>;
+
 struct LoggingNotifier<N: Notifier> {
     inner: N,
+    format: LogFormat,
 }
 
 impl<N: Notifier> LoggingNotifier<N> {
     fn new(notifier: N) -> Self {
-        Self { inner: notifier }
+        Self::with_format(notifier, |name, message| {
+            format!("  [Log] {} sending: {}", name, message)
+        })
+    }
+
+    fn with_format<F>(notifier: N, format: F) -> Self
+    where
+        F: Fn(&str, &str) -> String + Send + Sync + 'static,
+    {
+        Self {
+            inner: notifier,
+            format: Box::new(format),
+        }
+    }
+
+    fn format_line(&self, message: &str) -> String {
+        (self.format)(self.inner.name(), message)
     }
 }
 
 impl<N: Notifier + Send + Sync> Notifier for LoggingNotifier<N> {
     fn send(&self, message: &str) -> Result<(), String> {
-        println!("  [Log] {} sending: {}", self.inner.name(), message);
+        println!("{}", self.format_line(message));
         let result = self.inner.send(message);
         match &result {
             Ok(()) => println!("  [Log] Success"),
@@ -112,6 +137,200 @@ impl<N: Notifier + Send + Sync> Notifier for TimingNotifier<N> {
     }
 }
 
+// Fills `{message}` and `{time}` in a template; other `{...}` text is kept as-is
+struct TemplateNotifier<N: Notifier> {
+    inner: N,
+    template: String,
+}
+
+impl<N: Notifier> TemplateNotifier<N> {
+    fn new(notifier: N, template: &str) -> Self {
+        Self {
+            inner: notifier,
+            template: template.to_string(),
+        }
+    }
+
+    fn render(&self, message: &str, time: &str) -> String {
+        let mut out = String::with_capacity(self.template.len() + message.len());
+        let mut rest = self.template.as_str();
+        while let Some(open) = rest.find('{') {
+            out.push_str(&rest[..open]);
+            let after = &rest[open..];
+            let Some(close) = after.find('}') else {
+                rest = after; // Unclosed brace: the remainder is literal
+                break;
+            };
+            let value = match &after[1..close] {
+                "message" => message,
+                "time" => time,
+                _ => {
+                    // Not a placeholder: keep the brace and rescan right after it
+                    out.push('{');
+                    rest = &after[1..];
+                    continue;
+                }
+            };
+            out.push_str(value);
+            rest = &after[close + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+// UTC wall-clock time of day, e.g. "14:03:27 UTC"
+fn current_time() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format!(
+        "{:02}:{:02}:{:02} UTC",
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+impl<N: Notifier + Send + Sync> Notifier for TemplateNotifier<N> {
+    fn send(&self, message: &str) -> Result<(), String> {
+        self.inner.send(&self.render(message, &current_time()))
+    }
+    fn name(&self) -> &str {
+        "TemplateNotifier"
+    }
+}
+
+struct DedupNotifier<N: Notifier> {
+    inner: N,
+    window: Duration,
+    last_sent: Mutex<HashMap<String, Instant>>, // Message -> time last sent
+    suppressed: AtomicUsize,
+}
+
+impl<N: Notifier> DedupNotifier<N> {
+    fn new(notifier: N, window: Duration) -> Self {
+        Self {
+            inner: notifier,
+            window,
+            last_sent: Mutex::new(HashMap::new()),
+            suppressed: AtomicUsize::new(0),
+        }
+    }
+
+    fn suppressed_count(&self) -> usize {
+        self.suppressed.load(Ordering::Relaxed)
+    }
+}
+
+impl<N: Notifier + Send + Sync> Notifier for DedupNotifier<N> {
+    fn send(&self, message: &str) -> Result<(), String> {
+        let now = Instant::now();
+        {
+            let mut last_sent = self.last_sent.lock().unwrap();
+            // Forget expired messages so the map only holds the current window
+            last_sent.retain(|_, sent_at| now.duration_since(*sent_at) < self.window);
+            if last_sent.contains_key(message) {
+                println!("  [Dedup] Suppressed duplicate: {}", message);
+                self.suppressed.fetch_add(1, Ordering::Relaxed);
+                return Ok(());
+            }
+            // Claimed before sending, so a concurrent duplicate is suppressed too
+            last_sent.insert(message.to_string(), now);
+        }
+
+        let result = self.inner.send(message);
+        if result.is_err() {
+            // Not delivered: release the claim so a retry goes through
+            let mut last_sent = self.last_sent.lock().unwrap();
+            if last_sent.get(message) == Some(&now) {
+                last_sent.remove(message);
+            }
+        }
+        result
+    }
+    fn name(&self) -> &str {
+        "DedupNotifier"
+    }
+}
+
+// Higher priority first; the sequence number keeps equal priorities FIFO
+type Queued = (u8, Reverse<u64>, String);
+
+struct AsyncDispatcher<N: Notifier> {
+    inner: N,
+    queue: Mutex<BinaryHeap<Queued>>,
+    next_seq: AtomicU64,
+    notify: Notify,
+    closed: AtomicBool,
+}
+
+impl<N: Notifier> AsyncDispatcher<N> {
+    fn new(notifier: N) -> Arc<Self> {
+        Arc::new(Self {
+            inner: notifier,
+            queue: Mutex::new(BinaryHeap::new()),
+            next_seq: AtomicU64::new(0),
+            notify: Notify::new(),
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    fn enqueue(&self, priority: u8, message: &str) {
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        self.queue
+            .lock()
+            .unwrap()
+            .push((priority, Reverse(seq), message.to_string()));
+        self.notify.notify_one();
+    }
+
+    // The worker drains what is already queued before it exits
+    fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.notify.notify_one();
+    }
+
+    async fn run(self: Arc<Self>) {
+        loop {
+            let next = self.queue.lock().unwrap().pop();
+            match next {
+                Some((priority, _, message)) => {
+                    if let Err(e) = self.inner.send(&format!("[p{}] {}", priority, message)) {
+                        println!("  [Dispatch] Failed: {}", e);
+                    }
+                    tokio::task::yield_now().await;
+                }
+                None if self.closed.load(Ordering::Acquire) => break,
+                // A notify_one() that raced ahead of us leaves a permit, so no wakeup is lost
+                None => self.notify.notified().await,
+            }
+        }
+    }
+}
+
+fn demonstrate_async_dispatch() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    runtime.block_on(async {
+        let dispatcher = AsyncDispatcher::new(EmailNotifier::new("oncall@company.com"));
+        dispatcher.enqueue(1, "Weekly report ready");
+        dispatcher.enqueue(9, "Database is down");
+
+        let worker = tokio::spawn(Arc::clone(&dispatcher).run());
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        dispatcher.enqueue(1, "Backup finished");
+        dispatcher.enqueue(5, "Latency above SLO");
+        dispatcher.close();
+        worker.await.unwrap();
+    });
+}
+
 fn main() {
     println!("=== Basic Notifier ===\n");
     let email = EmailNotifier::new("user@example.com");
@@ -121,6 +340,18 @@ fn main() {
     let logged = LoggingNotifier::new(EmailNotifier::new("user@example.com"));
     logged.send("Hello with logging!").unwrap();
 
+    println!("\n=== Logging with a JSON Format ===\n");
+    let json_logged = LoggingNotifier::with_format(EmailNotifier::new("user@example.com"), |name, message| {
+        serde_json::json!({ "level": "info", "notifier": name, "message": message }).to_string()
+    });
+    json_logged.send("Hello \"JSON\" logs!").unwrap();
+    let line = json_logged.format_line("Hello \"JSON\" logs!");
+    let parsed: Result<serde_json::Value, _> = serde_json::from_str(&line);
+    println!(
+        "  Valid JSON with message: {}",
+        parsed.is_ok_and(|v| v["message"] == "Hello \"JSON\" logs!")
+    );
+
     println!("\n=== Timing Decorator ===\n");
     let timed = TimingNotifier::new(EmailNotifier::new("user@example.com"));
     timed.send("Hello with timing!").unwrap();
@@ -136,4 +367,141 @@ fn main() {
         Duration::from_millis(50),
     )));
     full.send("Critical notification!").unwrap();
+
+    println!("\n=== Deduplication Decorator ===\n");
+    let dedup = DedupNotifier::new(EmailNotifier::new("oncall@company.com"), Duration::from_millis(100));
+    dedup.send("Disk almost full").unwrap();
+    dedup.send("Disk almost full").unwrap();
+    std::thread::sleep(Duration::from_millis(150));
+    dedup.send("Disk almost full").unwrap();
+    println!("  Suppressed: {}", dedup.suppressed_count());
+
+    println!("\n=== Template Decorator ===\n");
+    let templated = TemplateNotifier::new(
+        LoggingNotifier::new(EmailNotifier::new("oncall@company.com")),
+        "Alert: {message} at {time} (runbook: {runbook})",
+    );
+    templated.send("Disk almost full").unwrap();
+    let rendered = templated.render("CPU hot", "09:30:00 UTC");
+    println!("  Rendered with fixed time: {}", rendered);
+    println!(
+        "  Matches expected: {}",
+        rendered == "Alert: CPU hot at 09:30:00 UTC (runbook: {runbook})"
+    );
+
+    println!("\n=== Priority Dispatch ===\n");
+    demonstrate_async_dispatch();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fails the first `failures` sends, then records every delivered message
+    struct ScriptedNotifier {
+        failures: AtomicUsize,
+        delivered: Mutex<Vec<String>>,
+    }
+
+    impl ScriptedNotifier {
+        fn failing(failures: usize) -> Self {
+            Self {
+                failures: AtomicUsize::new(failures),
+                delivered: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl Notifier for ScriptedNotifier {
+        fn send(&self, message: &str) -> Result<(), String> {
+            let remaining = self.failures.load(Ordering::SeqCst);
+            if remaining > 0 {
+                self.failures.store(remaining - 1, Ordering::SeqCst);
+                return Err("transient failure".to_string());
+            }
+            self.delivered.lock().unwrap().push(message.to_string());
+            Ok(())
+        }
+        fn name(&self) -> &str {
+            "ScriptedNotifier"
+        }
+    }
+
+    #[test]
+    fn dedup_retry_after_failed_send_is_delivered() {
+        let dedup = DedupNotifier::new(ScriptedNotifier::failing(1), Duration::from_secs(60));
+        assert!(dedup.send("Disk almost full").is_err());
+        assert!(dedup.send("Disk almost full").is_ok());
+        assert_eq!(*dedup.inner.delivered.lock().unwrap(), ["Disk almost full"]);
+        assert_eq!(dedup.suppressed_count(), 0);
+    }
+
+    #[test]
+    fn dedup_suppresses_within_window_only() {
+        let dedup = DedupNotifier::new(ScriptedNotifier::failing(0), Duration::from_millis(50));
+        dedup.send("a").unwrap();
+        dedup.send("a").unwrap();
+        dedup.send("b").unwrap();
+        assert_eq!(dedup.suppressed_count(), 1);
+
+        std::thread::sleep(Duration::from_millis(80));
+        dedup.send("a").unwrap();
+        assert_eq!(*dedup.inner.delivered.lock().unwrap(), ["a", "b", "a"]);
+    }
+
+    #[test]
+    fn dedup_prunes_expired_entries() {
+        let dedup = DedupNotifier::new(ScriptedNotifier::failing(0), Duration::from_millis(20));
+        for i in 0..100 {
+            dedup.send(&format!("message {}", i)).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(40));
+        dedup.send("latest").unwrap();
+        assert_eq!(dedup.last_sent.lock().unwrap().len(), 1);
+    }
+
+    fn render(template: &str, message: &str) -> String {
+        TemplateNotifier::new(ScriptedNotifier::failing(0), template).render(message, "12:00")
+    }
+
+    #[test]
+    fn template_substitutes_placeholders() {
+        assert_eq!(
+            render("[{time}] {message} {unknown}", "hi"),
+            "[12:00] hi {unknown}"
+        );
+    }
+
+    #[test]
+    fn template_stray_brace_does_not_swallow_placeholder() {
+        assert_eq!(render("{a {message}", "hi"), "{a hi");
+        assert_eq!(render("{message} {", "hi"), "hi {");
+    }
+
+    #[tokio::test]
+    async fn dispatcher_delivers_by_priority_then_fifo() {
+        let dispatcher = AsyncDispatcher::new(ScriptedNotifier::failing(0));
+        for (priority, message) in [(1, "a"), (5, "b"), (1, "c"), (9, "d"), (5, "e")] {
+            dispatcher.enqueue(priority, message);
+        }
+        dispatcher.close();
+        Arc::clone(&dispatcher).run().await;
+
+        assert_eq!(
+            *dispatcher.inner.delivered.lock().unwrap(),
+            ["[p9] d", "[p5] b", "[p5] e", "[p1] a", "[p1] c"]
+        );
+    }
+
+    #[tokio::test]
+    async fn dispatcher_delivers_messages_enqueued_while_waiting() {
+        let dispatcher = AsyncDispatcher::new(ScriptedNotifier::failing(0));
+        let worker = tokio::spawn(Arc::clone(&dispatcher).run());
+        tokio::task::yield_now().await;
+
+        dispatcher.enqueue(1, "late");
+        dispatcher.close();
+        worker.await.unwrap();
+        assert_eq!(*dispatcher.inner.delivered.lock().unwrap(), ["[p1] late"]);
+    }
 }
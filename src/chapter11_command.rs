@@ -4,8 +4,12 @@ trait Command {
     fn execute(&mut self, text: &mut String);
     fn undo(&mut self, text: &mut String);
     fn description(&self) -> String;
+    fn clone_box(&self) -> Box<dyn Command>;
+    // The edit as plain data; only meaningful once the command has executed
+    fn as_editor_command(&self) -> EditorCommand;
 }
 
+#[derive(Clone)]
 struct InsertText {
     position: usize,
     text: String,
@@ -32,8 +36,20 @@ impl Command for InsertText {
     fn description(&self) -> String {
         format!("Insert '{}' at {}", self.text, self.position)
     }
+
+    fn clone_box(&self) -> Box<dyn Command> {
+        Box::new(self.clone())
+    }
+
+    fn as_editor_command(&self) -> EditorCommand {
+        EditorCommand::Insert {
+            position: self.position,
+            text: self.text.clone(),
+        }
+    }
 }
 
+#[derive(Clone)]
 struct DeleteText {
     position: usize,
     length: usize,
@@ -62,12 +78,30 @@ impl Command for DeleteText {
     fn description(&self) -> String {
         format!("Delete {} chars at {}", self.length, self.position)
     }
+
+    fn clone_box(&self) -> Box<dyn Command> {
+        Box::new(self.clone())
+    }
+
+    fn as_editor_command(&self) -> EditorCommand {
+        EditorCommand::Delete {
+            position: self.position,
+            text: self.deleted.clone(),
+        }
+    }
+}
+
+// A recorded sequence of commands that can be replayed any number of times
+struct Macro {
+    commands: Vec<Box<dyn Command>>,
 }
 
 struct TextEditor {
     content: String,
     history: Vec<Box<dyn Command>>,
     undo_stack: Vec<Box<dyn Command>>,
+    recording: Option<Vec<Box<dyn Command>>>,
+    journal: Vec<EditorCommand>, // Edits applied since the last checkpoint
 }
 
 impl TextEditor {
@@ -76,12 +110,18 @@ impl TextEditor {
             content: String::new(),
             history: Vec::new(),
             undo_stack: Vec::new(),
+            recording: None,
+            journal: Vec::new(),
         }
     }
 
     fn execute(&mut self, mut command: Box<dyn Command>) {
         println!("Execute: {}", command.description());
+        if let Some(recorded) = &mut self.recording {
+            recorded.push(command.clone_box());
+        }
         command.execute(&mut self.content);
+        self.journal.push(command.as_editor_command());
         self.history.push(command);
         self.undo_stack.clear();
     }
@@ -90,6 +130,7 @@ impl TextEditor {
         if let Some(mut command) = self.history.pop() {
             println!("Undo: {}", command.description());
             command.undo(&mut self.content);
+            self.journal.push(command.as_editor_command().reverse());
             self.undo_stack.push(command);
         } else {
             println!("Nothing to undo");
@@ -100,6 +141,7 @@ impl TextEditor {
         if let Some(mut command) = self.undo_stack.pop() {
             println!("Redo: {}", command.description());
             command.execute(&mut self.content);
+            self.journal.push(command.as_editor_command());
             self.history.push(command);
         } else {
             println!("Nothing to redo");
@@ -109,6 +151,34 @@ impl TextEditor {
     fn content(&self) -> &str {
         &self.content
     }
+
+    // Marks the current content as saved; later edits form the next delta
+    fn checkpoint(&mut self) {
+        println!("Checkpoint ({} edits since last)", self.journal.len());
+        self.journal.clear();
+    }
+
+    fn pending_delta(&self) -> Vec<EditorCommand> {
+        self.journal.clone()
+    }
+
+    fn start_recording(&mut self) {
+        println!("Recording started");
+        self.recording = Some(Vec::new());
+    }
+
+    fn stop_recording(&mut self) -> Macro {
+        let commands = self.recording.take().unwrap_or_default();
+        println!("Recording stopped ({} commands)", commands.len());
+        Macro { commands }
+    }
+
+    fn play(&mut self, macro_: &Macro) {
+        println!("Playing macro ({} commands)", macro_.commands.len());
+        for command in &macro_.commands {
+            self.execute(command.clone_box());
+        }
+    }
 }
 
 // Enum-based command (simpler for closed set of commands)
@@ -177,6 +247,47 @@ fn main() {
     editor.redo();
     println!("Content: '{}'\n", editor.content());
 
+    println!("=== Macro Recording ===\n");
+
+    let mut editor = TextEditor::new();
+
+    editor.start_recording();
+    editor.execute(Box::new(InsertText::new(0, "Hello")));
+    editor.execute(Box::new(InsertText::new(5, ", Macro")));
+    let greeting = editor.stop_recording();
+    println!("Content: '{}'\n", editor.content());
+
+    let len = editor.content().len();
+    editor.execute(Box::new(DeleteText::new(0, len)));
+    println!("Content: '{}'\n", editor.content());
+
+    editor.play(&greeting);
+    println!("Content: '{}'\n", editor.content());
+
+    println!("=== Delta Persistence ===\n");
+
+    let mut editor = TextEditor::new();
+    editor.execute(Box::new(InsertText::new(0, "Draft")));
+    let mut saved = editor.content().to_string();
+    editor.checkpoint();
+
+    editor.execute(Box::new(InsertText::new(5, " one")));
+    editor.execute(Box::new(DeleteText::new(0, 6)));
+    editor.undo();
+
+    // Persist only the delta, then rebuild from the last saved state
+    for cmd in editor.pending_delta() {
+        cmd.apply(&mut saved);
+    }
+    println!("Replayed delta: '{}' (matches: {})\n", saved, saved == editor.content());
+    editor.checkpoint();
+
+    editor.execute(Box::new(InsertText::new(9, ", two")));
+    for cmd in editor.pending_delta() {
+        cmd.apply(&mut saved);
+    }
+    println!("Replayed delta: '{}' (matches: {})\n", saved, saved == editor.content());
+
     println!("=== Enum-Based Command Pattern ===\n");
 
     let mut content = String::from("Hello World");
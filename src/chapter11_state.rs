@@ -1,6 +1,6 @@
 //! Chapter 11: Behavioral Patterns - State Pattern (Typestate)
 
-use std::marker::PhantomData;
+use std::time::{Duration, SystemTime};
 
 // Typestate pattern for document workflow
 mod typestate {
@@ -8,19 +8,28 @@ mod typestate {
 
     pub struct Draft;
     pub struct PendingReview;
-    pub struct Approved;
-    pub struct Rejected;
+
+    // Review outcomes carry their metadata in the state itself
+    pub struct Approved {
+        reviewer: String,
+        approved_at: SystemTime,
+    }
+
+    pub struct Rejected {
+        reviewer: String,
+        reason: String,
+    }
 
     pub struct Document<State> {
         content: String,
-        _state: PhantomData<State>,
+        state: State,
     }
 
     impl Document<Draft> {
         pub fn new(content: &str) -> Self {
             Self {
                 content: content.to_string(),
-                _state: PhantomData,
+                state: Draft,
             }
         }
 
@@ -32,25 +41,31 @@ mod typestate {
             println!("Document submitted for review");
             Document {
                 content: self.content,
-                _state: PhantomData,
+                state: PendingReview,
             }
         }
     }
 
     impl Document<PendingReview> {
-        pub fn approve(self) -> Document<Approved> {
-            println!("Document approved");
+        pub fn approve(self, reviewer: &str) -> Document<Approved> {
+            println!("Document approved by {}", reviewer);
             Document {
                 content: self.content,
-                _state: PhantomData,
+                state: Approved {
+                    reviewer: reviewer.to_string(),
+                    approved_at: SystemTime::now(),
+                },
             }
         }
 
-        pub fn reject(self, reason: &str) -> Document<Rejected> {
-            println!("Document rejected: {}", reason);
+        pub fn reject(self, reviewer: &str, reason: &str) -> Document<Rejected> {
+            println!("Document rejected by {}: {}", reviewer, reason);
             Document {
                 content: self.content,
-                _state: PhantomData,
+                state: Rejected {
+                    reviewer: reviewer.to_string(),
+                    reason: reason.to_string(),
+                },
             }
         }
     }
@@ -59,14 +74,30 @@ mod typestate {
         pub fn publish(&self) {
             println!("Publishing: {}", self.content);
         }
+
+        pub fn reviewer(&self) -> &str {
+            &self.state.reviewer
+        }
+
+        pub fn approved_at(&self) -> SystemTime {
+            self.state.approved_at
+        }
     }
 
     impl Document<Rejected> {
+        pub fn reviewer(&self) -> &str {
+            &self.state.reviewer
+        }
+
+        pub fn reason(&self) -> &str {
+            &self.state.reason
+        }
+
         pub fn revise(self) -> Document<Draft> {
             println!("Document returned to draft for revision");
             Document {
                 content: self.content,
-                _state: PhantomData,
+                state: Draft,
             }
         }
     }
@@ -80,6 +111,8 @@ mod typestate {
 
 // Enum-based state machine
 mod enum_state {
+    use std::time::{Duration, SystemTime};
+
     #[derive(Debug, Clone)]
     pub enum ConnectionState {
         Disconnected,
@@ -90,12 +123,14 @@ mod enum_state {
 
     pub struct Connection {
         state: ConnectionState,
+        last_pong: Option<SystemTime>, // Only meaningful while Connected
     }
 
     impl Connection {
         pub fn new() -> Self {
             Self {
                 state: ConnectionState::Disconnected,
+                last_pong: None,
             }
         }
 
@@ -125,6 +160,7 @@ mod enum_state {
                 self.state = ConnectionState::Connected {
                     session_id: session_id.to_string(),
                 };
+                self.last_pong = Some(SystemTime::now());
                 println!("Connected with session: {}", session_id);
             }
         }
@@ -138,11 +174,35 @@ mod enum_state {
             }
         }
 
+        // Times are passed in so heartbeats can be simulated without waiting
+        pub fn ping(&mut self, now: SystemTime) {
+            if let ConnectionState::Connected { .. } = &self.state {
+                self.last_pong = Some(now);
+            }
+        }
+
+        pub fn check_health(&mut self, now: SystemTime, max_idle: Duration) {
+            if let ConnectionState::Connected { session_id } = &self.state {
+                let idle = self
+                    .last_pong
+                    .and_then(|pong| now.duration_since(pong).ok())
+                    .unwrap_or_default();
+                if idle > max_idle {
+                    println!("Session {} idle for {:?}, marking failed", session_id, idle);
+                    self.state = ConnectionState::Failed {
+                        error: "ping timeout".to_string(),
+                    };
+                    self.last_pong = None;
+                }
+            }
+        }
+
         pub fn disconnect(&mut self) {
             match &self.state {
                 ConnectionState::Connected { session_id } => {
                     println!("Disconnecting session: {}", session_id);
                     self.state = ConnectionState::Disconnected;
+                    self.last_pong = None;
                 }
                 _ => {
                     self.state = ConnectionState::Disconnected;
@@ -174,20 +234,27 @@ fn main() {
     let pending = doc.submit_for_review();
     // doc.edit("Can't edit"); // Won't compile - not in Draft state
 
-    let approved = pending.approve();
-    // pending.reject("reason"); // Won't compile - pending was moved
+    let approved = pending.approve("alice");
+    // pending.reject("bob", "reason"); // Won't compile - pending was moved
 
     approved.publish();
+    let age = approved.approved_at().elapsed().unwrap_or_default();
+    println!("Approved by {} ({:?} ago)", approved.reviewer(), age);
 
     println!("\n=== Rejection Flow ===\n");
 
     let doc2 = typestate::Document::<typestate::Draft>::new("Bad content");
     let pending2 = doc2.submit_for_review();
-    let rejected = pending2.reject("Needs more detail");
+    let rejected = pending2.reject("bob", "Needs more detail");
+    println!(
+        "{} asked for changes: {}",
+        rejected.reviewer(),
+        rejected.reason()
+    );
     let mut revised = rejected.revise();
     revised.edit("Better content");
     let pending3 = revised.submit_for_review();
-    let approved2 = pending3.approve();
+    let approved2 = pending3.approve("bob");
     approved2.publish();
 
     println!("\n=== Enum-Based Connection State ===\n");
@@ -207,4 +274,24 @@ fn main() {
     conn.connect();
     conn.on_failure("Network timeout");
     println!("State: {:?}", conn.state());
+
+    println!("\n=== Keepalive Pings ===\n");
+
+    let max_idle = Duration::from_secs(30);
+    let start = SystemTime::now();
+    let at = |secs| start + Duration::from_secs(secs);
+
+    conn.connect();
+    conn.on_success("sess_def456");
+    for secs in [20, 40, 60] {
+        conn.ping(at(secs));
+        conn.check_health(at(secs + 5), max_idle);
+        println!("t+{}s: {:?}", secs + 5, conn.state());
+    }
+
+    // The peer goes quiet: the next check finds a 40s gap
+    conn.check_health(at(100), max_idle);
+    if let enum_state::ConnectionState::Failed { error } = conn.state() {
+        println!("t+100s: connection failed ({})", error);
+    }
 }
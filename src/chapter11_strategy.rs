@@ -1,8 +1,33 @@
 //! Chapter 11: Behavioral Patterns - Strategy Pattern
 
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+const MAX_REFUND: f64 = 500.0;
+
 trait PaymentStrategy {
     fn pay(&self, amount: f64) -> Result<String, String>;
     fn name(&self) -> &str;
+
+    fn refund(&self, _amount: f64) -> Result<String, String> {
+        Err(format!("{}: refunds not supported", self.name()))
+    }
+}
+
+fn check_refund_limit(amount: f64) -> Result<(), String> {
+    if amount <= 0.0 || !amount.is_finite() {
+        Err(format!("Refund amount must be positive, got {}", amount))
+    } else if amount > MAX_REFUND {
+        Err(format!(
+            "Refund of ${:.2} exceeds limit of ${:.2}",
+            amount, MAX_REFUND
+        ))
+    } else {
+        Ok(())
+    }
 }
 
 struct CreditCardPayment {
@@ -28,6 +53,14 @@ impl PaymentStrategy for CreditCardPayment {
     fn name(&self) -> &str {
         "Credit Card"
     }
+    fn refund(&self, amount: f64) -> Result<String, String> {
+        check_refund_limit(amount)?;
+        Ok(format!(
+            "Refunded ${:.2} to credit card ending {}",
+            amount,
+            &self.card_number[self.card_number.len() - 4..]
+        ))
+    }
 }
 
 struct PayPalPayment {
@@ -49,6 +82,75 @@ impl PaymentStrategy for PayPalPayment {
     fn name(&self) -> &str {
         "PayPal"
     }
+    fn refund(&self, amount: f64) -> Result<String, String> {
+        check_refund_limit(amount)?;
+        Ok(format!("Refunded ${:.2} via PayPal ({})", amount, self.email))
+    }
+}
+
+// Uses the default refund: gift card payments are final
+struct GiftCardPayment {
+    code: String,
+}
+
+impl GiftCardPayment {
+    fn new(code: &str) -> Self {
+        Self {
+            code: code.to_string(),
+        }
+    }
+}
+
+impl PaymentStrategy for GiftCardPayment {
+    fn pay(&self, amount: f64) -> Result<String, String> {
+        Ok(format!("Paid ${:.2} with gift card {}", amount, self.code))
+    }
+    fn name(&self) -> &str {
+        "Gift Card"
+    }
+}
+
+// Test double for retry/circuit-breaker code: fails at a configurable rate.
+// A seeded splitmix64 generator makes the failure pattern reproducible.
+struct FlakyPayment {
+    failure_rate: f64,
+    delay: Duration,
+    rng_state: Cell<u64>,
+}
+
+impl FlakyPayment {
+    fn new(seed: u64, failure_rate: f64, delay: Duration) -> Self {
+        Self {
+            failure_rate,
+            delay,
+            rng_state: Cell::new(seed),
+        }
+    }
+
+    // Uniform in [0, 1)
+    fn next_random(&self) -> f64 {
+        let state = self.rng_state.get().wrapping_add(0x9E37_79B9_7F4A_7C15);
+        self.rng_state.set(state);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl PaymentStrategy for FlakyPayment {
+    fn pay(&self, amount: f64) -> Result<String, String> {
+        thread::sleep(self.delay);
+        if self.next_random() < self.failure_rate {
+            Err(format!("{}: simulated network failure", self.name()))
+        } else {
+            Ok(format!("Paid ${:.2} via flaky gateway", amount))
+        }
+    }
+    fn name(&self) -> &str {
+        "Flaky Gateway"
+    }
 }
 
 struct ShoppingCart {
@@ -75,6 +177,56 @@ impl ShoppingCart {
         }
         strategy.pay(total)
     }
+
+    fn checkout_split(
+        &self,
+        allocations: &[(f64, &dyn PaymentStrategy)],
+    ) -> Result<Vec<String>, String> {
+        let total = self.total();
+        if total <= 0.0 {
+            return Err("Cart is empty".to_string());
+        }
+
+        let allocated: f64 = allocations.iter().map(|(amount, _)| amount).sum();
+        if (allocated - total).abs() >= 0.01 {
+            return Err(format!(
+                "Allocations sum to ${:.2} but cart total is ${:.2}",
+                allocated, total
+            ));
+        }
+
+        if let Some((amount, strategy)) = allocations
+            .iter()
+            .find(|(amount, _)| amount.is_nan() || *amount <= 0.0)
+        {
+            return Err(format!(
+                "Allocation of ${:.2} to {} must be positive",
+                amount,
+                strategy.name()
+            ));
+        }
+
+        let mut receipts = Vec::new();
+        for (i, (amount, strategy)) in allocations.iter().enumerate() {
+            match strategy.pay(*amount) {
+                Ok(receipt) => receipts.push(receipt),
+                Err(e) => {
+                    // Undo the payments that already went through, newest first
+                    let mut error = format!("{} failed: {}", strategy.name(), e);
+                    for (amount, charged) in allocations[..i].iter().rev() {
+                        match charged.refund(*amount) {
+                            Ok(receipt) => error.push_str(&format!("; {}", receipt)),
+                            Err(refund_error) => {
+                                error.push_str(&format!("; refund failed: {}", refund_error))
+                            }
+                        }
+                    }
+                    return Err(error);
+                }
+            }
+        }
+        Ok(receipts)
+    }
 }
 
 // Closure-based strategy
@@ -95,6 +247,33 @@ impl PriceCalculator {
     }
 }
 
+// Closures can't be hashed, so callers name each strategy with a key
+struct CachingCalculator {
+    inner: PriceCalculator,
+    results: RefCell<HashMap<String, f64>>,
+}
+
+impl CachingCalculator {
+    fn new(inner: PriceCalculator) -> Self {
+        Self {
+            inner,
+            results: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn calculate<F>(&self, key: &str, discount_strategy: F) -> f64
+    where
+        F: Fn(f64) -> f64,
+    {
+        if let Some(&price) = self.results.borrow().get(key) {
+            return price;
+        }
+        let price = self.inner.calculate(discount_strategy);
+        self.results.borrow_mut().insert(key.to_string(), price);
+        price
+    }
+}
+
 fn no_discount(price: f64) -> f64 {
     price
 }
@@ -103,6 +282,23 @@ fn percentage_discount(percent: f64) -> impl Fn(f64) -> f64 {
     move |price| price * (1.0 - percent / 100.0)
 }
 
+// Sorting strategies: the algorithm is fixed, the comparison is pluggable
+fn sort_with<T>(items: &mut [T], strategy: &dyn Fn(&T, &T) -> Ordering) {
+    items.sort_by(strategy);
+}
+
+fn by_length() -> impl Fn(&String, &String) -> Ordering {
+    |a, b| a.chars().count().cmp(&b.chars().count())
+}
+
+fn case_insensitive() -> impl Fn(&String, &String) -> Ordering {
+    |a, b| a.to_lowercase().cmp(&b.to_lowercase())
+}
+
+fn reversed<T>(strategy: impl Fn(&T, &T) -> Ordering) -> impl Fn(&T, &T) -> Ordering {
+    move |a, b| strategy(b, a)
+}
+
 fn main() {
     println!("=== Payment Strategies ===\n");
 
@@ -115,6 +311,7 @@ fn main() {
     let strategies: Vec<Box<dyn PaymentStrategy>> = vec![
         Box::new(CreditCardPayment::new("4111111111111234")),
         Box::new(PayPalPayment::new("user@example.com")),
+        Box::new(GiftCardPayment::new("GIFT-2024")),
     ];
 
     for strategy in &strategies {
@@ -125,6 +322,51 @@ fn main() {
         }
     }
 
+    println!("\n=== Refunds ===\n");
+
+    for strategy in &strategies {
+        match strategy.refund(49.99) {
+            Ok(msg) => println!("  {}", msg),
+            Err(e) => println!("  Error: {}", e),
+        }
+    }
+    match strategies[0].refund(1000.0) {
+        Ok(msg) => println!("  {}", msg),
+        Err(e) => println!("  Error: {}", e),
+    }
+
+    println!("\n=== Split Payment ===\n");
+
+    let card = CreditCardPayment::new("4111111111111234");
+    let paypal = PayPalPayment::new("user@example.com");
+    let total = cart.total();
+
+    match cart.checkout_split(&[(total * 0.4, &card), (total * 0.6, &paypal)]) {
+        Ok(receipts) => {
+            for receipt in receipts {
+                println!("  {}", receipt);
+            }
+        }
+        Err(e) => println!("  Error: {}", e),
+    }
+
+    match cart.checkout_split(&[(50.0, &card), (50.0, &paypal)]) {
+        Ok(receipts) => println!("  Unexpected success: {:?}", receipts),
+        Err(e) => println!("  Error: {}", e),
+    }
+
+    // The card is charged first, then refunded when the gateway declines
+    let declining = FlakyPayment::new(1, 1.0, Duration::ZERO);
+    match cart.checkout_split(&[(total * 0.5, &card), (total * 0.5, &declining)]) {
+        Ok(receipts) => println!("  Unexpected success: {:?}", receipts),
+        Err(e) => println!("  Error: {}", e),
+    }
+
+    match cart.checkout_split(&[(total, &card), (0.0, &paypal)]) {
+        Ok(receipts) => println!("  Unexpected success: {:?}", receipts),
+        Err(e) => println!("  Error: {}", e),
+    }
+
     println!("\n=== Discount Strategies (Closures) ===\n");
 
     let calc = PriceCalculator::new(100.0);
@@ -133,4 +375,164 @@ fn main() {
     println!("No discount: ${:.2}", calc.calculate(no_discount));
     println!("10% off: ${:.2}", calc.calculate(percentage_discount(10.0)));
     println!("25% off: ${:.2}", calc.calculate(percentage_discount(25.0)));
+
+    println!("\n=== Sorting Strategies ===\n");
+
+    let mut words: Vec<String> = ["pear", "Fig", "banana", "apple", "kiwi"]
+        .iter()
+        .map(|w| w.to_string())
+        .collect();
+
+    sort_with(&mut words, &by_length());
+    println!("By length: {:?}", words);
+    sort_with(&mut words, &case_insensitive());
+    println!("Case-insensitive: {:?}", words);
+    sort_with(&mut words, &reversed(|a: &String, b: &String| a.cmp(b)));
+    println!("Reverse alphabetical: {:?}", words);
+    sort_with(&mut words, &reversed(by_length()));
+    println!("Longest first: {:?}", words);
+
+    println!("\n=== Cached Discount Strategies ===\n");
+
+    let cached = CachingCalculator::new(PriceCalculator::new(100.0));
+    let runs = Cell::new(0);
+    let expensive_discount = |price: f64| {
+        runs.set(runs.get() + 1);
+        price * 0.8
+    };
+
+    println!("loyalty: ${:.2}", cached.calculate("loyalty", expensive_discount));
+    println!("loyalty: ${:.2}", cached.calculate("loyalty", expensive_discount));
+    println!("Strategy ran {} time(s)", runs.get());
+
+    println!("\n=== Flaky Downstream ===\n");
+
+    // Same seed, same pattern: deterministic enough to assert against
+    let mut patterns = Vec::new();
+    for run in 1..=2 {
+        let flaky = FlakyPayment::new(42, 0.3, Duration::from_millis(5));
+        let pattern: String = (0..10)
+            .map(|_| if flaky.pay(10.0).is_ok() { 'S' } else { 'F' })
+            .collect();
+        println!("Run {} with seed 42: {}", run, pattern);
+        patterns.push(pattern);
+    }
+    assert_eq!(patterns[0], patterns[1]);
+    let other = FlakyPayment::new(7, 0.3, Duration::ZERO);
+    let pattern: String = (0..10)
+        .map(|_| if other.pay(10.0).is_ok() { 'S' } else { 'F' })
+        .collect();
+    println!("Run with seed 7:    {}", pattern);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Records every charge and refund; declines payments when `decline` is set
+    struct RecordingPayment {
+        decline: bool,
+        log: RefCell<Vec<String>>,
+    }
+
+    impl RecordingPayment {
+        fn new(decline: bool) -> Self {
+            Self {
+                decline,
+                log: RefCell::new(Vec::new()),
+            }
+        }
+    }
+
+    impl PaymentStrategy for RecordingPayment {
+        fn pay(&self, amount: f64) -> Result<String, String> {
+            if self.decline {
+                return Err("declined".to_string());
+            }
+            self.log.borrow_mut().push(format!("pay {:.2}", amount));
+            Ok(format!("paid {:.2}", amount))
+        }
+        fn name(&self) -> &str {
+            "Recording"
+        }
+        fn refund(&self, amount: f64) -> Result<String, String> {
+            self.log.borrow_mut().push(format!("refund {:.2}", amount));
+            Ok(format!("refunded {:.2}", amount))
+        }
+    }
+
+    fn cart_of(total: f64) -> ShoppingCart {
+        let mut cart = ShoppingCart::new();
+        cart.add_item("item", total);
+        cart
+    }
+
+    #[test]
+    fn split_charges_every_allocation() {
+        let (a, b) = (RecordingPayment::new(false), RecordingPayment::new(false));
+        let receipts = cart_of(100.0)
+            .checkout_split(&[(40.0, &a), (60.0, &b)])
+            .unwrap();
+        assert_eq!(receipts, ["paid 40.00", "paid 60.00"]);
+    }
+
+    #[test]
+    fn split_refunds_earlier_payments_on_failure() {
+        let first = RecordingPayment::new(false);
+        let second = RecordingPayment::new(false);
+        let declining = RecordingPayment::new(true);
+        let result =
+            cart_of(100.0).checkout_split(&[(30.0, &first), (30.0, &second), (40.0, &declining)]);
+
+        assert!(result.is_err());
+        assert_eq!(*first.log.borrow(), ["pay 30.00", "refund 30.00"]);
+        assert_eq!(*second.log.borrow(), ["pay 30.00", "refund 30.00"]);
+    }
+
+    #[test]
+    fn split_rejects_non_positive_allocations_before_charging() {
+        let a = RecordingPayment::new(false);
+        let b = RecordingPayment::new(false);
+        for bad in [0.0, -10.0, f64::NAN] {
+            let result = cart_of(100.0).checkout_split(&[(100.0 - bad, &a), (bad, &b)]);
+            assert!(result.is_err());
+        }
+        assert!(a.log.borrow().is_empty());
+        assert!(b.log.borrow().is_empty());
+    }
+
+    #[test]
+    fn refunds_must_be_positive_and_within_limit() {
+        let card = CreditCardPayment::new("4111111111111111");
+        assert!(card.refund(50.0).is_ok());
+        assert!(card.refund(MAX_REFUND).is_ok());
+        for bad in [0.0, -50.0, f64::NAN, f64::INFINITY, MAX_REFUND + 0.01] {
+            assert!(card.refund(bad).is_err(), "refund of {} accepted", bad);
+        }
+        assert!(PayPalPayment::new("a@b.c").refund(-50.0).is_err());
+    }
+
+    fn flaky_pattern(seed: u64) -> String {
+        let flaky = FlakyPayment::new(seed, 0.3, Duration::ZERO);
+        (0..10)
+            .map(|_| if flaky.pay(10.0).is_ok() { 'S' } else { 'F' })
+            .collect()
+    }
+
+    #[test]
+    fn flaky_payment_pattern_is_pinned_by_seed() {
+        assert_eq!(flaky_pattern(42), "SFFSFSFSSS");
+        assert_eq!(flaky_pattern(42), flaky_pattern(42));
+        assert_eq!(flaky_pattern(7), "SFSSSFSSFS");
+    }
+
+    #[test]
+    fn flaky_payment_rates_zero_and_one_are_absolute() {
+        let reliable = FlakyPayment::new(1, 0.0, Duration::ZERO);
+        let broken = FlakyPayment::new(1, 1.0, Duration::ZERO);
+        for _ in 0..20 {
+            assert!(reliable.pay(1.0).is_ok());
+            assert!(broken.pay(1.0).is_err());
+        }
+    }
 }
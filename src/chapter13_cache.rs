@@ -1,29 +1,61 @@
 //! Chapter 13: Concurrency Foundations - Thread-Safe Cache
 
 use std::collections::HashMap;
+use std::future::Future;
 use std::hash::Hash;
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
 use std::thread;
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+// Slower backing storage behind the in-memory cache (L1 cache, L2 store)
+trait Store<K, V>: Send + Sync {
+    fn load(&self, key: &K) -> Option<V>;
+    fn save(&self, key: &K, value: &V);
+}
 
 struct Cache<K, V> {
     data: RwLock<HashMap<K, V>>,
+    store: Option<Box<dyn Store<K, V>>>,
 }
 
 impl<K: Eq + Hash + Clone, V: Clone> Cache<K, V> {
     fn new() -> Self {
         Self {
             data: RwLock::new(HashMap::new()),
+            store: None,
+        }
+    }
+
+    fn with_store(store: impl Store<K, V> + 'static) -> Self {
+        Self {
+            data: RwLock::new(HashMap::new()),
+            store: Some(Box::new(store)),
         }
     }
 
     fn get(&self, key: &K) -> Option<V> {
-        let data = self.data.read().unwrap();
-        data.get(key).cloned()
+        {
+            let data = self.data.read().unwrap();
+            if let Some(value) = data.get(key) {
+                return Some(value.clone());
+            }
+        }
+
+        // L1 miss: fall through to the store. The load runs unlocked, so a
+        // concurrent insert may have filled the slot meanwhile; that value is newer.
+        let loaded = self.store.as_ref()?.load(key)?;
+        let mut data = self.data.write().unwrap();
+        Some(data.entry(key.clone()).or_insert(loaded).clone())
     }
 
     fn insert(&self, key: K, value: V) {
+        // Save under the write lock so concurrent inserts reach the store and L1 in the same order
         let mut data = self.data.write().unwrap();
+        if let Some(store) = &self.store {
+            store.save(&key, &value);
+        }
         data.insert(key, value);
     }
 
@@ -31,12 +63,9 @@ impl<K: Eq + Hash + Clone, V: Clone> Cache<K, V> {
     where
         F: FnOnce() -> V,
     {
-        // Try read first
-        {
-            let data = self.data.read().unwrap();
-            if let Some(value) = data.get(&key) {
-                return value.clone();
-            }
+        // Try read first (memory, then store)
+        if let Some(value) = self.get(&key) {
+            return value;
         }
 
         // Need to compute and insert
@@ -47,10 +76,68 @@ impl<K: Eq + Hash + Clone, V: Clone> Cache<K, V> {
         }
 
         let value = f();
+        if let Some(store) = &self.store {
+            store.save(&key, &value);
+        }
         data.insert(key, value.clone());
         value
     }
 
+    // Like get_or_insert_with, but errors are returned and never cached
+    fn get_or_try_insert_with<F, E>(&self, key: K, f: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Result<V, E>,
+    {
+        if let Some(value) = self.get(&key) {
+            return Ok(value);
+        }
+
+        let mut data = self.data.write().unwrap();
+        if let Some(value) = data.get(&key) {
+            return Ok(value.clone());
+        }
+
+        let value = f()?;
+        if let Some(store) = &self.store {
+            store.save(&key, &value);
+        }
+        data.insert(key, value.clone());
+        Ok(value)
+    }
+
+    // Returns None if `token` fires first; a cancelled computation is never cached.
+    // Callers don't share in-flight work, so one caller's cancellation never
+    // affects another computing the same key. No lock is held across the await.
+    async fn get_or_compute_cancellable<F>(
+        &self,
+        key: K,
+        fut: F,
+        token: CancellationToken,
+    ) -> Option<V>
+    where
+        F: Future<Output = V>,
+    {
+        if let Some(value) = self.get(&key) {
+            return Some(value);
+        }
+
+        let value = tokio::select! {
+            _ = token.cancelled() => return None,
+            value = fut => value,
+        };
+
+        let mut data = self.data.write().unwrap();
+        // Another caller may have finished first; keep its value
+        if let Some(existing) = data.get(&key) {
+            return Some(existing.clone());
+        }
+        if let Some(store) = &self.store {
+            store.save(&key, &value);
+        }
+        data.insert(key, value.clone());
+        Some(value)
+    }
+
     fn len(&self) -> usize {
         let data = self.data.read().unwrap();
         data.len()
@@ -60,6 +147,101 @@ impl<K: Eq + Hash + Clone, V: Clone> Cache<K, V> {
         let mut data = self.data.write().unwrap();
         data.clear();
     }
+
+    // Drops the in-memory copy only; a backing store keeps its value
+    fn invalidate(&self, key: &K) -> bool {
+        let mut data = self.data.write().unwrap();
+        data.remove(key).is_some()
+    }
+
+    // HashMap keeps its allocation after removals; give the excess back
+    fn compact(&self) {
+        let mut data = self.data.write().unwrap();
+        data.shrink_to_fit();
+    }
+
+    fn capacity(&self) -> usize {
+        let data = self.data.read().unwrap();
+        data.capacity()
+    }
+}
+
+// Holds Weak references, so the cache never keeps a large value alive on its own.
+// Once the last Arc elsewhere is dropped, the entry reads as a miss.
+struct WeakCache<K, V> {
+    data: RwLock<HashMap<K, Weak<V>>>,
+}
+
+impl<K: Eq + Hash, V> WeakCache<K, V> {
+    fn new() -> Self {
+        Self {
+            data: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn insert(&self, key: K, value: &Arc<V>) {
+        let mut data = self.data.write().unwrap();
+        data.insert(key, Arc::downgrade(value));
+    }
+
+    fn get(&self, key: &K) -> Option<Arc<V>> {
+        {
+            let data = self.data.read().unwrap();
+            if let Some(value) = data.get(key)?.upgrade() {
+                return Some(value);
+            }
+        }
+
+        // Dead entry: drop it so it stops taking up a slot
+        let mut data = self.data.write().unwrap();
+        // Re-check: another thread may have inserted a live value meanwhile
+        if data.get(key).is_some_and(|weak| weak.strong_count() == 0) {
+            data.remove(key);
+        }
+        data.get(key).and_then(Weak::upgrade)
+    }
+
+    // Entries whose value is gone still occupy a slot until looked up or purged
+    fn purge_dead(&self) -> usize {
+        let mut data = self.data.write().unwrap();
+        let before = data.len();
+        data.retain(|_, weak| weak.strong_count() > 0);
+        before - data.len()
+    }
+
+    fn len(&self) -> usize {
+        let data = self.data.read().unwrap();
+        data.len()
+    }
+}
+
+// In-memory stand-in for a database or disk, counting how often it is used
+struct MemoryStore {
+    data: Mutex<HashMap<String, String>>,
+    loads: AtomicUsize,
+    saves: AtomicUsize,
+}
+
+impl MemoryStore {
+    fn new() -> Self {
+        Self {
+            data: Mutex::new(HashMap::new()),
+            loads: AtomicUsize::new(0),
+            saves: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl Store<String, String> for Arc<MemoryStore> {
+    fn load(&self, key: &String) -> Option<String> {
+        self.loads.fetch_add(1, Ordering::Relaxed);
+        self.data.lock().unwrap().get(key).cloned()
+    }
+
+    fn save(&self, key: &String, value: &String) {
+        self.saves.fetch_add(1, Ordering::Relaxed);
+        self.data.lock().unwrap().insert(key.clone(), value.clone());
+    }
 }
 
 fn expensive_computation(n: u64) -> u64 {
@@ -80,6 +262,52 @@ fn expensive_computation(n: u64) -> u64 {
     }
 }
 
+async fn build_report(caller: &str) -> String {
+    println!("  [{}] Building report...", caller);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    format!("report built for {}", caller)
+}
+
+fn demonstrate_cancellable_compute() {
+    println!("\n=== Cancellation-aware computation ===\n");
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    runtime.block_on(async {
+        let reports: Arc<Cache<String, String>> = Arc::new(Cache::new());
+        let impatient = CancellationToken::new();
+        let patient = CancellationToken::new();
+
+        let a = {
+            let reports = Arc::clone(&reports);
+            let token = impatient.clone();
+            tokio::spawn(async move {
+                reports
+                    .get_or_compute_cancellable("q3".to_string(), build_report("A"), token)
+                    .await
+            })
+        };
+        let b = {
+            let reports = Arc::clone(&reports);
+            tokio::spawn(async move {
+                reports
+                    .get_or_compute_cancellable("q3".to_string(), build_report("B"), patient)
+                    .await
+            })
+        };
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        impatient.cancel();
+
+        println!("Caller A (cancelled): {:?}", a.await.unwrap());
+        println!("Caller B: {:?}", b.await.unwrap());
+        println!("Cached: {:?}", reports.get(&"q3".to_string()));
+    });
+}
+
 fn main() {
     println!("=== Thread-Safe Cache ===\n");
 
@@ -117,4 +345,186 @@ fn main() {
     println!("fib(50) = {}", value);
 
     println!("\nFinal cache size: {}", cache.len());
+
+    println!("\n=== Fallible computation ===\n");
+
+    let configs: Cache<String, String> = Cache::new();
+    let mut attempts = 0;
+
+    for _ in 0..3 {
+        let result = configs.get_or_try_insert_with("remote".to_string(), || {
+            attempts += 1;
+            println!("  Fetching remote config (attempt {})...", attempts);
+            if attempts == 1 {
+                Err("connection reset")
+            } else {
+                Ok("timeout=30".to_string())
+            }
+        });
+        println!("Result: {:?}", result);
+    }
+
+    println!("Computation ran {} times", attempts);
+
+    println!("\n=== Write-through to a backing store ===\n");
+
+    let store = Arc::new(MemoryStore::new());
+    store
+        .data
+        .lock()
+        .unwrap()
+        .insert("theme".to_string(), "dark".to_string());
+
+    let settings: Cache<String, String> = Cache::with_store(Arc::clone(&store));
+
+    // Miss in memory, hit in the store: no computation needed
+    let theme = settings.get_or_insert_with("theme".to_string(), || {
+        println!("  Computing default theme...");
+        "light".to_string()
+    });
+    println!("theme = {}", theme);
+
+    settings.insert("language".to_string(), "en".to_string());
+    println!(
+        "Persisted language: {:?}",
+        store.data.lock().unwrap().get("language")
+    );
+
+    // Dropping the in-memory copy still leaves the store as a fallback
+    settings.clear();
+    println!(
+        "After clear, language = {:?}",
+        settings.get(&"language".to_string())
+    );
+    println!(
+        "Store loads: {}, saves: {}",
+        store.loads.load(Ordering::Relaxed),
+        store.saves.load(Ordering::Relaxed)
+    );
+
+    println!("\n=== Compacting after bulk invalidation ===\n");
+
+    let sessions: Cache<u64, String> = Cache::new();
+    for id in 0..10_000 {
+        sessions.insert(id, format!("session-{}", id));
+    }
+    for id in 100..10_000 {
+        sessions.invalidate(&id);
+    }
+    println!(
+        "Entries: {}, capacity before compact: {}",
+        sessions.len(),
+        sessions.capacity()
+    );
+    sessions.compact();
+    println!(
+        "Entries: {}, capacity after compact:  {}",
+        sessions.len(),
+        sessions.capacity()
+    );
+
+    println!("\n=== Weak-valued cache ===\n");
+
+    let images: WeakCache<&str, Vec<u8>> = WeakCache::new();
+    let logo = Arc::new(vec![0u8; 4 * 1024 * 1024]);
+    let banner = Arc::new(vec![0u8; 8 * 1024 * 1024]);
+    images.insert("logo", &logo);
+    images.insert("banner", &banner);
+
+    println!(
+        "logo while in use: {:?} bytes",
+        images.get(&"logo").map(|v| v.len())
+    );
+
+    // The cache alone does not keep the 4 MB buffer alive
+    drop(logo);
+    println!(
+        "logo after last Arc dropped: {:?}",
+        images.get(&"logo").map(|v| v.len())
+    );
+
+    drop(banner);
+    println!("Entries before purge: {}", images.len());
+    println!("Purged {} dead entries", images.purge_dead());
+    println!("Entries after purge: {}", images.len());
+
+    demonstrate_cancellable_compute();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{self, Receiver, Sender};
+
+    // Blocks inside `load` until released, returning a value that is stale by then
+    struct GatedStore {
+        stale: u32,
+        entered: Mutex<Sender<()>>,
+        release: Mutex<Receiver<()>>,
+    }
+
+    impl Store<u32, u32> for GatedStore {
+        fn load(&self, _key: &u32) -> Option<u32> {
+            self.entered.lock().unwrap().send(()).unwrap();
+            self.release.lock().unwrap().recv().unwrap();
+            Some(self.stale)
+        }
+
+        fn save(&self, _key: &u32, _value: &u32) {}
+    }
+
+    #[test]
+    fn slow_load_does_not_overwrite_concurrent_insert() {
+        let (entered_tx, entered_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel();
+        let cache = Cache::with_store(GatedStore {
+            stale: 1,
+            entered: Mutex::new(entered_tx),
+            release: Mutex::new(release_rx),
+        });
+
+        let loaded = thread::scope(|s| {
+            let reader = s.spawn(|| cache.get(&7));
+            entered_rx.recv().unwrap();
+            cache.insert(7, 2);
+            release_tx.send(()).unwrap();
+            reader.join().unwrap()
+        });
+
+        assert_eq!(loaded, Some(2));
+        assert_eq!(cache.get(&7), Some(2));
+    }
+
+    #[test]
+    fn concurrent_inserts_leave_store_and_memory_in_agreement() {
+        for _ in 0..20 {
+            let store = Arc::new(MemoryStore::new());
+            let cache = Cache::with_store(Arc::clone(&store));
+            thread::scope(|s| {
+                for t in 0..8 {
+                    let cache = &cache;
+                    s.spawn(move || {
+                        for i in 0..50 {
+                            cache.insert("key".to_string(), format!("{}-{}", t, i));
+                        }
+                    });
+                }
+            });
+
+            let in_memory = cache.get(&"key".to_string());
+            let in_store = store.data.lock().unwrap().get("key").cloned();
+            assert_eq!(in_memory, in_store);
+        }
+    }
+
+    #[test]
+    fn miss_is_filled_from_store_once() {
+        let store = Arc::new(MemoryStore::new());
+        store.save(&"a".to_string(), &"from store".to_string());
+        let cache = Cache::with_store(Arc::clone(&store));
+
+        assert_eq!(cache.get(&"a".to_string()).as_deref(), Some("from store"));
+        assert_eq!(cache.get(&"a".to_string()).as_deref(), Some("from store"));
+        assert_eq!(store.loads.load(Ordering::Relaxed), 1);
+    }
 }
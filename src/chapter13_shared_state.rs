@@ -1,6 +1,7 @@
 //! Chapter 13: Concurrency Foundations - Shared State
 
-use std::sync::{Arc, Mutex, RwLock};
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, RwLock};
 use std::thread;
 use std::time::Duration;
 
@@ -31,15 +32,15 @@ fn demonstrate_arc_mutex() {
     println!("\nFinal count: {}", *counter.lock().unwrap());
 }
 
+#[derive(Debug, Clone)]
+struct Config {
+    max_connections: u32,
+    timeout_ms: u64,
+}
+
 fn demonstrate_rwlock() {
     println!("\n=== RwLock for Read-Heavy Workloads ===\n");
 
-    #[derive(Debug)]
-    struct Config {
-        max_connections: u32,
-        timeout_ms: u64,
-    }
-
     let config = Arc::new(RwLock::new(Config {
         max_connections: 100,
         timeout_ms: 5000,
@@ -118,8 +119,231 @@ fn demonstrate_deadlock_prevention() {
     println!("No deadlock occurred!");
 }
 
+// Copy-on-write snapshots: readers hold the lock only long enough to clone an Arc
+struct CowConfig {
+    current: RwLock<Arc<Config>>,
+}
+
+impl CowConfig {
+    fn new(config: Config) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(config)),
+        }
+    }
+
+    fn current(&self) -> Arc<Config> {
+        Arc::clone(&self.current.read().unwrap())
+    }
+
+    fn update(&self, f: impl FnOnce(&Config) -> Config) {
+        let mut current = self.current.write().unwrap();
+        let next = f(&current);
+        *current = Arc::new(next);
+    }
+}
+
+fn demonstrate_cow_config() {
+    println!("\n=== Copy-on-Write Config Snapshots ===\n");
+
+    // Invariant: timeout_ms is always max_connections * 50
+    let config = Arc::new(CowConfig::new(Config {
+        max_connections: 100,
+        timeout_ms: 5000,
+    }));
+
+    let mut handles = vec![];
+
+    for i in 0..3 {
+        let config = Arc::clone(&config);
+        handles.push(thread::spawn(move || {
+            let mut torn = 0;
+            for _ in 0..1000 {
+                let snapshot = config.current();
+                if snapshot.timeout_ms != snapshot.max_connections as u64 * 50 {
+                    torn += 1;
+                }
+            }
+            println!("Reader {}: 1000 reads, {} torn", i, torn);
+        }));
+    }
+
+    {
+        let config = Arc::clone(&config);
+        handles.push(thread::spawn(move || {
+            for _ in 0..100 {
+                config.update(|old| Config {
+                    max_connections: old.max_connections + 1,
+                    timeout_ms: (old.max_connections as u64 + 1) * 50,
+                });
+            }
+            println!("Writer: swapped in 100 new configs");
+        }));
+    }
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    println!("\nFinal snapshot: {:?}", config.current());
+}
+
+// Recover access to a mutex whose previous holder panicked
+fn lock_recover<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|poisoned| {
+        println!("Warning: mutex was poisoned, recovering inner value");
+        poisoned.into_inner()
+    })
+}
+
+fn demonstrate_poison_recovery() {
+    println!("\n=== Recovering from a Poisoned Mutex ===\n");
+
+    let data = Mutex::new(vec![1, 2, 3]);
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                let mut guard = data.lock().unwrap();
+                guard.push(4);
+                panic!("Thread panicked while holding the lock");
+            }));
+            println!("Panicking thread caught: {}", result.is_err());
+        });
+    });
+
+    println!("Mutex poisoned: {}", data.is_poisoned());
+    println!("Plain lock() is an error: {}", data.lock().is_err());
+
+    let mut guard = lock_recover(&data);
+    guard.push(5);
+    println!("Recovered data: {:?}", *guard);
+}
+
+// Classic producer/consumer queue: pop sleeps on the condvar instead of spinning
+struct BlockingQueue<T> {
+    items: Mutex<VecDeque<T>>,
+    not_empty: Condvar,
+}
+
+impl<T> BlockingQueue<T> {
+    fn new() -> Self {
+        Self {
+            items: Mutex::new(VecDeque::new()),
+            not_empty: Condvar::new(),
+        }
+    }
+
+    fn push(&self, item: T) {
+        self.items.lock().unwrap().push_back(item);
+        self.not_empty.notify_one();
+    }
+
+    fn pop(&self) -> T {
+        // wait_while re-checks the condition, so spurious wakeups are handled
+        let mut items = self
+            .not_empty
+            .wait_while(self.items.lock().unwrap(), |items| items.is_empty())
+            .unwrap();
+        items.pop_front().unwrap()
+    }
+}
+
+fn demonstrate_blocking_queue() {
+    println!("\n=== Condvar Blocking Queue ===\n");
+
+    let queue = Arc::new(BlockingQueue::new());
+
+    let consumer = {
+        let queue = Arc::clone(&queue);
+        thread::spawn(move || {
+            println!("Consumer waiting on empty queue...");
+            for _ in 0..3 {
+                let job: String = queue.pop();
+                println!("Consumer woke up with: {}", job);
+            }
+        })
+    };
+
+    for job in ["resize image", "send email", "rebuild index"] {
+        thread::sleep(Duration::from_millis(50));
+        println!("Producer pushing: {}", job);
+        queue.push(job.to_string());
+    }
+
+    consumer.join().unwrap();
+}
+
+// Scoped threads borrow `data` directly: no Arc, no copying, no locks
+fn parallel_sum(data: &[u64], threads: usize) -> u64 {
+    if data.is_empty() {
+        return 0;
+    }
+    let chunk_size = data.len().div_ceil(threads.max(1));
+
+    thread::scope(|s| {
+        let handles: Vec<_> = data
+            .chunks(chunk_size)
+            .map(|chunk| s.spawn(move || chunk.iter().sum::<u64>()))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    })
+}
+
+fn demonstrate_parallel_sum() {
+    println!("\n=== Scoped Parallel Sum ===\n");
+
+    let data: Vec<u64> = (1..=1_000_000).collect();
+    let sequential: u64 = data.iter().sum();
+
+    for threads in [1, 4, 7] {
+        let parallel = parallel_sum(&data, threads);
+        println!(
+            "{} threads: {} (matches sequential: {})",
+            threads,
+            parallel,
+            parallel == sequential
+        );
+    }
+
+    // More threads than elements just means one element per thread
+    println!("16 threads over 3 items: {}", parallel_sum(&[1, 2, 3], 16));
+    println!("Empty slice: {}", parallel_sum(&[], 4));
+}
+
 fn main() {
     demonstrate_arc_mutex();
     demonstrate_rwlock();
+    demonstrate_cow_config();
     demonstrate_deadlock_prevention();
+    demonstrate_poison_recovery();
+    demonstrate_parallel_sum();
+    demonstrate_blocking_queue();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_recover_returns_data_from_poisoned_mutex() {
+        let data = Mutex::new(vec![1, 2, 3]);
+        let _ = thread::scope(|s| {
+            s.spawn(|| {
+                let mut guard = data.lock().unwrap();
+                guard.push(4);
+                panic!("poison the mutex");
+            })
+            .join()
+        });
+
+        assert!(data.is_poisoned());
+        assert_eq!(*lock_recover(&data), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn lock_recover_on_healthy_mutex() {
+        let data = Mutex::new(7);
+        *lock_recover(&data) += 1;
+        assert_eq!(*data.lock().unwrap(), 8);
+    }
 }
@@ -1,7 +1,9 @@
 //! Chapter 14: Message Passing - Actor Pattern
 
-use std::sync::mpsc::{self, Sender};
+use std::collections::HashMap;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 // Actor messages
 enum CounterMessage {
@@ -9,6 +11,8 @@ enum CounterMessage {
     Decrement,
     Get(Sender<i64>),
     Reset,
+    Snapshot(Sender<i64>),
+    Restore(i64),
     Stop,
 }
 
@@ -42,6 +46,15 @@ impl CounterActor {
                     self.value = 0;
                     println!("[CounterActor] Reset to 0");
                 }
+                CounterMessage::Snapshot(reply_tx) => {
+                    println!("[CounterActor] Snapshot taken at {}", self.value);
+                    let _ = reply_tx.send(self.value);
+                }
+                CounterMessage::Restore(value) => {
+                    // Overwrites unconditionally: a restore is authoritative
+                    self.value = value;
+                    println!("[CounterActor] Restored to {}", self.value);
+                }
                 CounterMessage::Stop => {
                     println!("[CounterActor] Stopping");
                     break;
@@ -89,11 +102,179 @@ impl CounterHandle {
         let _ = self.sender.send(CounterMessage::Reset);
     }
 
+    fn snapshot(&self) -> i64 {
+        let (tx, rx) = mpsc::channel();
+        let _ = self.sender.send(CounterMessage::Snapshot(tx));
+        rx.recv().unwrap_or(0)
+    }
+
+    fn restore(&self, value: i64) {
+        let _ = self.sender.send(CounterMessage::Restore(value));
+    }
+
     fn stop(&self) {
         let _ = self.sender.send(CounterMessage::Stop);
     }
 }
 
+// Batching actor: coalesces increments and applies them in one flush
+enum BatchMessage {
+    Increment,
+    Stats(Sender<BatchStats>),
+    Stop,
+}
+
+struct BatchStats {
+    value: i64,
+    flushes: u32,
+}
+
+struct BatchingCounterActor {
+    receiver: mpsc::Receiver<BatchMessage>,
+    value: i64,
+    pending: i64,
+    batch_started: Option<Instant>,
+    batch_size: i64,
+    flush_interval: Duration,
+    flushes: u32,
+}
+
+impl BatchingCounterActor {
+    fn new(
+        receiver: mpsc::Receiver<BatchMessage>,
+        batch_size: i64,
+        flush_interval: Duration,
+    ) -> Self {
+        Self {
+            receiver,
+            value: 0,
+            pending: 0,
+            batch_started: None,
+            batch_size,
+            flush_interval,
+            flushes: 0,
+        }
+    }
+
+    fn flush(&mut self, trigger: &str) {
+        if self.pending == 0 {
+            return;
+        }
+        self.value += self.pending;
+        self.flushes += 1;
+        println!(
+            "[BatchingCounter] Flushed {} increments ({}), value now {}",
+            self.pending, trigger, self.value
+        );
+        self.pending = 0;
+        self.batch_started = None;
+    }
+
+    fn run(&mut self) {
+        println!("[BatchingCounter] Started");
+
+        loop {
+            // Wait indefinitely when idle, otherwise only until the open batch is due
+            let msg = match self.batch_started {
+                None => self
+                    .receiver
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+                Some(started) => {
+                    let due = started + self.flush_interval;
+                    self.receiver
+                        .recv_timeout(due.saturating_duration_since(Instant::now()))
+                }
+            };
+
+            match msg {
+                Ok(BatchMessage::Increment) => {
+                    self.batch_started.get_or_insert_with(Instant::now);
+                    self.pending += 1;
+                    if self.pending >= self.batch_size {
+                        self.flush("size");
+                    }
+                }
+                Ok(BatchMessage::Stats(reply_tx)) => {
+                    let _ = reply_tx.send(BatchStats {
+                        value: self.value,
+                        flushes: self.flushes,
+                    });
+                }
+                Err(RecvTimeoutError::Timeout) => self.flush("timeout"),
+                Ok(BatchMessage::Stop) | Err(RecvTimeoutError::Disconnected) => {
+                    self.flush("stop");
+                    break;
+                }
+            }
+        }
+
+        println!("[BatchingCounter] Stopped");
+    }
+}
+
+#[derive(Clone)]
+struct BatchingCounterHandle {
+    sender: Sender<BatchMessage>,
+}
+
+impl BatchingCounterHandle {
+    fn spawn(batch_size: i64, flush_interval: Duration) -> (Self, JoinHandle<()>) {
+        let (tx, rx) = mpsc::channel();
+
+        let handle = thread::spawn(move || {
+            let mut actor = BatchingCounterActor::new(rx, batch_size, flush_interval);
+            actor.run();
+        });
+
+        (Self { sender: tx }, handle)
+    }
+
+    fn increment(&self) {
+        let _ = self.sender.send(BatchMessage::Increment);
+    }
+
+    fn stats(&self) -> Option<BatchStats> {
+        let (tx, rx) = mpsc::channel();
+        let _ = self.sender.send(BatchMessage::Stats(tx));
+        rx.recv().ok()
+    }
+
+    fn stop(&self) {
+        let _ = self.sender.send(BatchMessage::Stop);
+    }
+}
+
+// Registry for addressing actors by name
+struct ActorRegistry {
+    actors: HashMap<String, CounterHandle>,
+}
+
+impl ActorRegistry {
+    fn new() -> Self {
+        Self {
+            actors: HashMap::new(),
+        }
+    }
+
+    fn register(&mut self, name: &str, handle: CounterHandle) {
+        self.actors.insert(name.to_string(), handle);
+    }
+
+    fn get(&self, name: &str) -> Result<&CounterHandle, String> {
+        self.actors
+            .get(name)
+            .ok_or_else(|| format!("No actor registered as '{}'", name))
+    }
+
+    fn send(&self, name: &str, msg: CounterMessage) -> Result<(), String> {
+        self.get(name)?
+            .sender
+            .send(msg)
+            .map_err(|_| format!("Actor '{}' is no longer running", name))
+    }
+}
+
 // Bank account actor example
 enum AccountMessage {
     Deposit(u64),
@@ -206,9 +387,81 @@ fn main() {
     counter.reset();
     println!("After reset: {}", counter.get());
 
+    println!("\n=== Snapshot and Restore ===\n");
+
+    for _ in 0..5 {
+        counter.increment();
+    }
+    let snapshot = counter.snapshot();
+    counter.reset();
+    counter.restore(snapshot);
+    let restored = counter.get();
+    println!("Snapshot {}, restored {}", snapshot, restored);
+
     counter.stop();
     counter_join.join().unwrap();
 
+    println!("\n=== Actor Registry ===\n");
+
+    let mut registry = ActorRegistry::new();
+    let mut joins = vec![];
+
+    for name in ["clicks", "views"] {
+        let (handle, join) = CounterHandle::spawn();
+        registry.register(name, handle);
+        joins.push(join);
+    }
+
+    registry.send("clicks", CounterMessage::Increment).unwrap();
+    registry.send("views", CounterMessage::Increment).unwrap();
+    registry.send("views", CounterMessage::Increment).unwrap();
+    registry.send("views", CounterMessage::Increment).unwrap();
+
+    println!("clicks = {}", registry.get("clicks").unwrap().get());
+    println!("views = {}", registry.get("views").unwrap().get());
+
+    if let Err(e) = registry.send("likes", CounterMessage::Increment) {
+        println!("Error: {}", e);
+    }
+
+    for name in ["clicks", "views"] {
+        registry.send(name, CounterMessage::Stop).unwrap();
+    }
+    for join in joins {
+        join.join().unwrap();
+    }
+
+    println!("\n=== Batching Actor ===\n");
+
+    let (batcher, batcher_join) = BatchingCounterHandle::spawn(10, Duration::from_millis(50));
+
+    // A small burst stays under the batch size, so it flushes on the timer
+    for _ in 0..3 {
+        batcher.increment();
+    }
+    thread::sleep(Duration::from_millis(100));
+    if let Some(stats) = batcher.stats() {
+        println!(
+            "After small burst: value={}, flushes={}",
+            stats.value, stats.flushes
+        );
+    }
+
+    // A large burst flushes every 10 messages, then the remainder on the timer
+    for _ in 0..25 {
+        batcher.increment();
+    }
+    thread::sleep(Duration::from_millis(100));
+    if let Some(stats) = batcher.stats() {
+        println!(
+            "After large burst: value={}, flushes={}",
+            stats.value, stats.flushes
+        );
+    }
+
+    batcher.stop();
+    batcher_join.join().unwrap();
+
     println!("\n=== Bank Account Actor ===\n");
 
     let (account, account_join) = AccountHandle::spawn("ACC-001");
@@ -233,3 +486,85 @@ fn main() {
     account.stop();
     account_join.join().unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_routes_messages_by_name() {
+        let mut registry = ActorRegistry::new();
+        let mut joins = vec![];
+        for name in ["clicks", "views"] {
+            let (handle, join) = CounterHandle::spawn();
+            registry.register(name, handle);
+            joins.push(join);
+        }
+
+        registry.send("clicks", CounterMessage::Increment).unwrap();
+        for _ in 0..3 {
+            registry.send("views", CounterMessage::Increment).unwrap();
+        }
+        assert_eq!(registry.get("clicks").unwrap().get(), 1);
+        assert_eq!(registry.get("views").unwrap().get(), 3);
+        assert!(registry.send("likes", CounterMessage::Increment).is_err());
+        assert!(registry.get("likes").is_err());
+
+        for name in ["clicks", "views"] {
+            registry.send(name, CounterMessage::Stop).unwrap();
+        }
+        for join in joins {
+            join.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn batching_actor_flushes_a_small_burst_once_on_the_timer() {
+        let (batcher, join) = BatchingCounterHandle::spawn(10, Duration::from_millis(30));
+        for _ in 0..3 {
+            batcher.increment();
+        }
+        thread::sleep(Duration::from_millis(100));
+
+        let stats = batcher.stats().unwrap();
+        assert_eq!(stats.value, 3);
+        assert_eq!(stats.flushes, 1);
+
+        batcher.stop();
+        join.join().unwrap();
+    }
+
+    #[test]
+    fn batching_actor_flushes_full_batches_by_size() {
+        // Long interval, so only the size trigger can fire before stats
+        let (batcher, join) = BatchingCounterHandle::spawn(10, Duration::from_secs(60));
+        for _ in 0..25 {
+            batcher.increment();
+        }
+
+        let stats = batcher.stats().unwrap();
+        assert_eq!(stats.value, 20);
+        assert_eq!(stats.flushes, 2);
+
+        batcher.stop();
+        join.join().unwrap();
+    }
+
+    #[test]
+    fn restore_brings_back_the_snapshot_value() {
+        let (counter, join) = CounterHandle::spawn();
+        for _ in 0..5 {
+            counter.increment();
+        }
+        let snapshot = counter.snapshot();
+        assert_eq!(snapshot, 5);
+
+        counter.reset();
+        counter.decrement();
+        counter.restore(snapshot);
+        assert_eq!(counter.get(), 5);
+
+        counter.stop();
+        join.join().unwrap();
+    }
+}
@@ -1,6 +1,6 @@
 //! Chapter 14: Message Passing - Pipeline Pattern
 
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::mpsc::{self, Receiver};
 use std::thread::{self, JoinHandle};
 
 fn spawn_stage<T, U, F>(
@@ -29,6 +29,83 @@ where
     (output_rx, handle)
 }
 
+// One item in, any number out; an empty Vec drops the item
+fn spawn_flatmap_stage<T, U, F>(
+    name: &'static str,
+    input: Receiver<T>,
+    transform: F,
+) -> (Receiver<U>, JoinHandle<()>)
+where
+    T: Send + 'static,
+    U: Send + 'static,
+    F: Fn(T) -> Vec<U> + Send + 'static,
+{
+    let (output_tx, output_rx) = mpsc::channel();
+
+    let handle = thread::spawn(move || {
+        'items: for item in input {
+            for result in transform(item) {
+                if output_tx.send(result).is_err() {
+                    break 'items;
+                }
+            }
+        }
+        println!("[{}] Stage finished", name);
+    });
+
+    (output_rx, handle)
+}
+
+// Retries each item up to max_attempts, then routes the original to a dead-letter channel
+fn spawn_retry_stage<T, U, F>(
+    name: &'static str,
+    input: Receiver<T>,
+    max_attempts: u32,
+    transform: F,
+) -> (Receiver<U>, Receiver<T>, JoinHandle<()>)
+where
+    T: Send + 'static,
+    U: Send + 'static,
+    F: Fn(&T) -> Result<U, String> + Send + 'static,
+{
+    assert!(max_attempts > 0, "max_attempts must be at least 1");
+    let (output_tx, output_rx) = mpsc::channel();
+    let (dead_tx, dead_rx) = mpsc::channel();
+
+    let handle = thread::spawn(move || {
+        for item in input {
+            let mut outcome = Err(String::new());
+            for attempt in 1..=max_attempts {
+                outcome = transform(&item);
+                match &outcome {
+                    Ok(_) => break,
+                    Err(e) => println!(
+                        "[{}] Attempt {}/{} failed: {}",
+                        name, attempt, max_attempts, e
+                    ),
+                }
+            }
+
+            match outcome {
+                Ok(result) => {
+                    if output_tx.send(result).is_err() {
+                        break; // Downstream is gone, nothing left to do
+                    }
+                }
+                Err(_) => {
+                    // Nobody is collecting failures; that must not stop the good items
+                    if dead_tx.send(item).is_err() {
+                        println!("[{}] Dead-letter receiver dropped, discarding item", name);
+                    }
+                }
+            }
+        }
+        println!("[{}] Stage finished", name);
+    });
+
+    (output_rx, dead_rx, handle)
+}
+
 #[derive(Debug, Clone)]
 struct LogEntry {
     level: String,
@@ -112,4 +189,139 @@ fn main() {
     collector.join().unwrap();
 
     println!("\nPipeline completed!");
+
+    println!("\n=== Retries and Dead Letters ===\n");
+
+    let (input_tx, input_rx) = mpsc::channel::<String>();
+
+    let (parsed_rx, dead_rx, retry_handle) =
+        spawn_retry_stage("retry-parser", input_rx, 3, |raw: &String| {
+            raw.parse::<u32>().map_err(|e| format!("'{}': {}", raw, e))
+        });
+
+    for raw in ["10", "twenty", "30", "4o"] {
+        input_tx.send(raw.to_string()).unwrap();
+    }
+    drop(input_tx);
+
+    retry_handle.join().unwrap();
+
+    let parsed: Vec<u32> = parsed_rx.iter().collect();
+    let dead: Vec<String> = dead_rx.iter().collect();
+    println!("\nParsed: {:?}", parsed);
+    println!("Dead letters: {:?}", dead);
+
+    println!("\n=== Flat-Map Stage ===\n");
+
+    let (input_tx, input_rx) = mpsc::channel::<String>();
+
+    let (words_rx, split_handle) = spawn_flatmap_stage("splitter", input_rx, |sentence: String| {
+        sentence
+            .split_whitespace()
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    });
+
+    let sentences = ["the quick brown fox", "", "jumps over", "the lazy dog"];
+    let expected: usize = sentences.iter().map(|s| s.split_whitespace().count()).sum();
+    for sentence in sentences {
+        input_tx.send(sentence.to_string()).unwrap();
+    }
+    drop(input_tx);
+
+    split_handle.join().unwrap();
+
+    let words: Vec<String> = words_rx.iter().collect();
+    println!("\nWords: {:?}", words);
+    println!("{} sentences -> {} words", sentences.len(), words.len());
+    assert_eq!(words.len(), expected, "one output per word");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    fn parse(raw: &String) -> Result<u32, String> {
+        raw.parse().map_err(|_| format!("not a number: {}", raw))
+    }
+
+    #[test]
+    fn retry_stage_dead_letters_inputs_that_exhaust_their_attempts() {
+        let attempts = Arc::new(Mutex::new(HashMap::new()));
+        let counted = Arc::clone(&attempts);
+        let (tx, rx) = mpsc::channel();
+        let (output, dead, handle) = spawn_retry_stage("test", rx, 3, move |raw: &String| {
+            *counted.lock().unwrap().entry(raw.clone()).or_insert(0) += 1;
+            parse(raw)
+        });
+
+        for raw in ["1", "x", "2", "y"] {
+            tx.send(raw.to_string()).unwrap();
+        }
+        drop(tx);
+        handle.join().unwrap();
+
+        assert_eq!(output.iter().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(dead.iter().collect::<Vec<_>>(), ["x", "y"]);
+        let attempts = attempts.lock().unwrap();
+        assert_eq!(attempts["x"], 3);
+        assert_eq!(attempts["y"], 3);
+        assert_eq!(attempts["1"], 1);
+        assert_eq!(attempts["2"], 1);
+    }
+
+    #[test]
+    fn retry_stage_keeps_going_without_dead_letter_receiver() {
+        let (tx, rx) = mpsc::channel();
+        let (output, dead, handle) = spawn_retry_stage("test", rx, 2, parse);
+        drop(dead);
+
+        for raw in ["1", "x", "2", "y", "3"] {
+            tx.send(raw.to_string()).unwrap();
+        }
+        drop(tx);
+
+        assert_eq!(output.iter().collect::<Vec<_>>(), [1, 2, 3]);
+        handle.join().unwrap();
+    }
+
+    fn split_words(sentences: &[&str]) -> Vec<String> {
+        let (tx, rx) = mpsc::channel::<String>();
+        let (words, handle) = spawn_flatmap_stage("test", rx, |sentence: String| {
+            sentence
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        });
+        for sentence in sentences {
+            tx.send(sentence.to_string()).unwrap();
+        }
+        drop(tx);
+        handle.join().unwrap();
+        words.iter().collect()
+    }
+
+    #[test]
+    fn flatmap_stage_emits_every_word_in_order() {
+        let sentences = ["the quick brown fox", "", "jumps over", "the lazy dog"];
+        let total: usize = sentences.iter().map(|s| s.split_whitespace().count()).sum();
+        let words = split_words(&sentences);
+        assert_eq!(words.len(), total);
+        assert_eq!(words[..4], ["the", "quick", "brown", "fox"]);
+        assert_eq!(words[4], "jumps");
+    }
+
+    #[test]
+    fn flatmap_stage_emits_nothing_for_an_empty_sentence() {
+        assert!(split_words(&[""]).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "max_attempts must be at least 1")]
+    fn retry_stage_rejects_zero_attempts() {
+        let (_tx, rx) = mpsc::channel::<String>();
+        let _ = spawn_retry_stage("test", rx, 0, parse);
+    }
 }
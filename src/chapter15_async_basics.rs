@@ -1,8 +1,15 @@
 //! Chapter 15: Async Rust - Basics
 
+use futures_util::stream::FuturesUnordered;
+use futures_util::{Stream, StreamExt, future};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
 use std::time::Duration;
-use tokio::sync::mpsc;
-use tokio::time::sleep;
+use tokio::sync::{Semaphore, mpsc, oneshot, watch};
+use tokio::time::{Instant, MissedTickBehavior, interval, sleep, timeout_at};
 
 async fn fetch_data(id: u32) -> String {
     println!("[Task {}] Starting fetch...", id);
@@ -22,6 +29,45 @@ async fn demonstrate_concurrent_tasks() {
     println!("  {}", r3);
 }
 
+async fn fetch_with_deadline(ids: &[u32], deadline: Duration) -> Vec<(u32, Option<String>)> {
+    let deadline = Instant::now() + deadline;
+
+    // Start every fetch up front so they all run concurrently
+    let handles: Vec<_> = ids
+        .iter()
+        .map(|&id| (id, tokio::spawn(fetch_data(id))))
+        .collect();
+
+    let mut results = Vec::with_capacity(handles.len());
+    for (id, mut handle) in handles {
+        match timeout_at(deadline, &mut handle).await {
+            Ok(Ok(data)) => results.push((id, Some(data))),
+            Ok(Err(_)) => results.push((id, None)),
+            Err(_) => {
+                println!("[Task {}] Missed the deadline, cancelling", id);
+                handle.abort();
+                results.push((id, None));
+            }
+        }
+    }
+    results
+}
+
+async fn demonstrate_partial_results() {
+    println!("\n=== Deadline with Partial Results ===\n");
+
+    // fetch_data(id) takes id * 100ms, so task 5 cannot make a 350ms deadline
+    let results = fetch_with_deadline(&[1, 2, 5], Duration::from_millis(350)).await;
+
+    println!("\nResults:");
+    for (id, result) in results {
+        match result {
+            Some(data) => println!("  {}: {}", id, data),
+            None => println!("  {}: <timed out>", id),
+        }
+    }
+}
+
 async fn demonstrate_spawned_tasks() {
     println!("\n=== Spawned Tasks ===\n");
 
@@ -69,6 +115,316 @@ async fn demonstrate_async_channels() {
     consumer.await.unwrap();
 }
 
+// Adapts the receiver to the Stream trait, unlocking map/filter/take and friends
+struct MessageStream {
+    rx: mpsc::Receiver<String>,
+}
+
+impl Stream for MessageStream {
+    type Item = String;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<String>> {
+        // The channel already speaks poll: None once every sender is gone
+        self.rx.poll_recv(cx)
+    }
+}
+
+fn into_stream(rx: mpsc::Receiver<String>) -> impl Stream<Item = String> {
+    MessageStream { rx }
+}
+
+async fn demonstrate_message_stream() {
+    println!("\n=== Receiver as a Stream ===\n");
+
+    let (tx, rx) = mpsc::channel::<String>(2);
+
+    let producer = tokio::spawn(async move {
+        for i in 1..=10 {
+            // Stops early once the stream (and with it the receiver) is dropped
+            if tx.send(format!("event {}", i)).await.is_err() {
+                println!("Receiver gone after {} sends", i - 1);
+                break;
+            }
+        }
+    });
+
+    let picked: Vec<String> = into_stream(rx)
+        .filter(|msg| future::ready(!msg.ends_with('1')))
+        .map(|msg| msg.to_uppercase())
+        .take(3)
+        .collect()
+        .await;
+
+    println!("Took {} items: {:?}", picked.len(), picked);
+    producer.await.unwrap();
+}
+
+async fn throttled_send<T>(tx: mpsc::Sender<T>, items: Vec<T>, min_interval: Duration) {
+    let mut ticker = interval(min_interval);
+    // Delay (not Burst) so a slow receiver never causes back-to-back sends
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    for item in items {
+        tokio::select! {
+            _ = ticker.tick() => {
+                if tx.send(item).await.is_err() {
+                    break;
+                }
+            }
+            _ = tx.closed() => {
+                println!("Receiver dropped, stopping early");
+                break;
+            }
+        }
+    }
+}
+
+async fn demonstrate_throttling() {
+    println!("\n=== Rate-Limited Producer ===\n");
+
+    let (tx, mut rx) = mpsc::channel::<u32>(10);
+    let min_interval = Duration::from_millis(50);
+    let start = Instant::now();
+
+    let producer = tokio::spawn(throttled_send(tx, (1..=5).collect(), min_interval));
+
+    while let Some(item) = rx.recv().await {
+        println!("Received {} at {:?}", item, start.elapsed());
+    }
+    producer.await.unwrap();
+
+    println!(
+        "5 items took {:?} (minimum {:?})",
+        start.elapsed(),
+        min_interval * 4
+    );
+}
+
+// Async bank account actor; a semaphore caps how many withdrawals are in flight
+enum AccountMessage {
+    Withdraw(u64, oneshot::Sender<Result<u64, String>>),
+    Balance(oneshot::Sender<u64>),
+}
+
+#[derive(Clone)]
+struct AsyncAccountHandle {
+    sender: mpsc::Sender<AccountMessage>,
+    permits: Arc<Semaphore>,
+    in_flight: Arc<AtomicUsize>,
+    peak_in_flight: Arc<AtomicUsize>,
+}
+
+impl AsyncAccountHandle {
+    fn spawn(initial_balance: u64, max_concurrent: usize) -> Self {
+        // Zero permits would make every withdraw wait forever
+        assert!(max_concurrent > 0, "max_concurrent must be non-zero");
+        let (tx, mut rx) = mpsc::channel(32);
+
+        tokio::spawn(async move {
+            let mut balance = initial_balance;
+            while let Some(msg) = rx.recv().await {
+                match msg {
+                    AccountMessage::Withdraw(amount, reply) => {
+                        sleep(Duration::from_millis(2)).await; // Simulate a ledger write
+                        let result = if amount <= balance {
+                            balance -= amount;
+                            Ok(amount)
+                        } else {
+                            Err("Insufficient funds".to_string())
+                        };
+                        let _ = reply.send(result);
+                    }
+                    AccountMessage::Balance(reply) => {
+                        let _ = reply.send(balance);
+                    }
+                }
+            }
+        });
+
+        Self {
+            sender: tx,
+            permits: Arc::new(Semaphore::new(max_concurrent)),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            peak_in_flight: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    async fn withdraw(&self, amount: u64) -> Result<u64, String> {
+        // Held until the reply arrives, so at most max_concurrent requests are outstanding
+        let _permit = self.permits.acquire().await.map_err(|e| e.to_string())?;
+        let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak_in_flight.fetch_max(now, Ordering::SeqCst);
+
+        let (tx, rx) = oneshot::channel();
+        let result = match self.sender.send(AccountMessage::Withdraw(amount, tx)).await {
+            Ok(()) => rx.await.unwrap_or(Err("Actor unavailable".to_string())),
+            Err(_) => Err("Actor unavailable".to_string()),
+        };
+
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        result
+    }
+
+    fn peak_in_flight(&self) -> usize {
+        self.peak_in_flight.load(Ordering::SeqCst)
+    }
+
+    async fn balance(&self) -> u64 {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.sender.send(AccountMessage::Balance(tx)).await;
+        rx.await.unwrap_or(0)
+    }
+}
+
+async fn demonstrate_guarded_account() {
+    println!("\n=== Semaphore-Guarded Async Account ===\n");
+
+    let account = AsyncAccountHandle::spawn(1000, 4);
+    let mut handles = vec![];
+
+    for _ in 0..50 {
+        let account = account.clone();
+        handles.push(tokio::spawn(async move { account.withdraw(30).await }));
+    }
+
+    let mut withdrawn = 0;
+    let mut rejected = 0;
+    for handle in handles {
+        match handle.await.unwrap() {
+            Ok(amount) => withdrawn += amount,
+            Err(_) => rejected += 1,
+        }
+    }
+
+    println!("Peak in-flight withdrawals: {}", account.peak_in_flight());
+    println!("Withdrawn: {} ({} requests rejected)", withdrawn, rejected);
+    println!("Remaining balance: {}", account.balance().await);
+}
+
+// Counter actor that publishes every change; a watch channel only keeps the
+// latest value, so slow subscribers see the current state rather than a backlog
+enum CounterCommand {
+    Add(i64),
+    Reset,
+}
+
+#[derive(Clone)]
+struct WatchedCounterHandle {
+    sender: mpsc::Sender<CounterCommand>,
+    value: watch::Receiver<i64>,
+}
+
+impl WatchedCounterHandle {
+    fn spawn() -> Self {
+        let (tx, mut rx) = mpsc::channel(32);
+        let (value_tx, value_rx) = watch::channel(0);
+
+        tokio::spawn(async move {
+            while let Some(cmd) = rx.recv().await {
+                match cmd {
+                    CounterCommand::Add(delta) => value_tx.send_modify(|v| *v += delta),
+                    CounterCommand::Reset => value_tx.send_modify(|v| *v = 0),
+                }
+            }
+        });
+
+        Self {
+            sender: tx,
+            value: value_rx,
+        }
+    }
+
+    fn subscribe(&self) -> watch::Receiver<i64> {
+        self.value.clone()
+    }
+
+    async fn increment(&self) {
+        let _ = self.sender.send(CounterCommand::Add(1)).await;
+    }
+
+    async fn decrement(&self) {
+        let _ = self.sender.send(CounterCommand::Add(-1)).await;
+    }
+
+    async fn reset(&self) {
+        let _ = self.sender.send(CounterCommand::Reset).await;
+    }
+}
+
+async fn demonstrate_watched_counter() {
+    println!("\n=== Watch-Based Live Counter ===\n");
+
+    let counter = WatchedCounterHandle::spawn();
+    let mut dashboard = counter.subscribe();
+
+    // Await each change instead of polling
+    for _ in 0..3 {
+        counter.increment().await;
+        dashboard.changed().await.unwrap();
+        println!("Dashboard sees: {}", *dashboard.borrow_and_update());
+    }
+    counter.decrement().await;
+    dashboard.changed().await.unwrap();
+    println!("Dashboard sees: {}", *dashboard.borrow_and_update());
+
+    // A late subscriber starts from the current value
+    let late = counter.subscribe();
+    println!("Late subscriber starts at: {}", *late.borrow());
+
+    counter.reset().await;
+    dashboard.changed().await.unwrap();
+    println!("After reset: {}", *dashboard.borrow());
+}
+
+type BoxedFetch<'a, T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + 'a>>;
+
+// Races redundant requests: the first Ok wins, and an error only surfaces once
+// every future has failed. Losers still running are dropped (cancelled).
+// Panics if `futs` is empty, since there would be neither a value nor an error.
+async fn first_ok<T, E>(futs: Vec<BoxedFetch<'_, T, E>>) -> Result<T, E> {
+    assert!(!futs.is_empty(), "first_ok needs at least one future");
+
+    let mut pending: FuturesUnordered<_> = futs.into_iter().collect();
+    let mut last_err = None;
+    while let Some(result) = pending.next().await {
+        match result {
+            Ok(value) => return Ok(value),
+            Err(e) => last_err = Some(e),
+        }
+    }
+    Err(last_err.expect("at least one future ran"))
+}
+
+async fn query_replica(name: &'static str, delay_ms: u64, healthy: bool) -> Result<String, String> {
+    sleep(Duration::from_millis(delay_ms)).await;
+    if healthy {
+        println!("[{}] answered after {}ms", name, delay_ms);
+        Ok(format!("value from {}", name))
+    } else {
+        println!("[{}] failed after {}ms", name, delay_ms);
+        Err(format!("{} unavailable", name))
+    }
+}
+
+async fn demonstrate_first_ok() {
+    println!("\n=== First Success Across Replicas ===\n");
+
+    let result = first_ok(vec![
+        Box::pin(query_replica("replica-a", 20, false)),
+        Box::pin(query_replica("replica-b", 60, true)),
+        Box::pin(query_replica("replica-c", 200, true)),
+    ])
+    .await;
+    println!("Result: {:?}", result);
+
+    let result = first_ok(vec![
+        Box::pin(query_replica("replica-a", 20, false)),
+        Box::pin(query_replica("replica-b", 40, false)),
+    ])
+    .await;
+    println!("All failed: {:?}", result);
+}
+
 async fn demonstrate_select() {
     println!("\n=== Select (Racing Futures) ===\n");
 
@@ -115,10 +471,165 @@ async fn demonstrate_timeout() {
 #[tokio::main]
 async fn main() {
     demonstrate_concurrent_tasks().await;
+    demonstrate_partial_results().await;
     demonstrate_spawned_tasks().await;
     demonstrate_async_channels().await;
+    demonstrate_message_stream().await;
+    demonstrate_throttling().await;
+    demonstrate_guarded_account().await;
+    demonstrate_watched_counter().await;
     demonstrate_select().await;
+    demonstrate_first_ok().await;
     demonstrate_timeout().await;
 
     println!("\n=== All async demos completed ===");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn guarded_account_never_overdraws_under_contention() {
+        let account = AsyncAccountHandle::spawn(1_000, 4);
+        let withdrawals: Vec<_> = (0..50)
+            .map(|_| {
+                let account = account.clone();
+                tokio::spawn(async move { account.withdraw(30).await })
+            })
+            .collect();
+
+        let mut withdrawn = 0;
+        for withdrawal in withdrawals {
+            if let Ok(amount) = withdrawal.await.unwrap() {
+                withdrawn += amount;
+            }
+        }
+        assert!(withdrawn <= 1_000);
+        assert_eq!(withdrawn, 33 * 30); // Every request succeeds until funds run out
+        assert_eq!(withdrawn, 1_000 - account.balance().await);
+        assert!((1..=4).contains(&account.peak_in_flight()));
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "max_concurrent must be non-zero")]
+    async fn guarded_account_rejects_zero_permits() {
+        AsyncAccountHandle::spawn(100, 0);
+    }
+
+    #[tokio::test]
+    async fn first_ok_returns_first_success_or_last_error() {
+        let futs: Vec<BoxedFetch<'_, u32, &str>> = vec![
+            Box::pin(async { Err("fast failure") }),
+            Box::pin(async {
+                sleep(Duration::from_millis(10)).await;
+                Ok(7)
+            }),
+        ];
+        assert_eq!(first_ok(futs).await, Ok(7));
+
+        let futs: Vec<BoxedFetch<'_, u32, &str>> = vec![
+            Box::pin(async { Err("first") }),
+            Box::pin(async {
+                sleep(Duration::from_millis(10)).await;
+                Err("last")
+            }),
+        ];
+        assert_eq!(first_ok(futs).await, Err("last"));
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "first_ok needs at least one future")]
+    async fn first_ok_rejects_nothing_to_race() {
+        let _ = first_ok::<u32, &str>(Vec::new()).await;
+    }
+
+    #[tokio::test]
+    async fn fetch_with_deadline_keeps_results_that_made_it() {
+        // fetch_data(id) takes id * 100ms
+        let results = fetch_with_deadline(&[1, 5, 2], Duration::from_millis(350)).await;
+        assert_eq!(
+            results,
+            [
+                (1, Some("Data from task 1".to_string())),
+                (5, None),
+                (2, Some("Data from task 2".to_string())),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn throttled_send_spaces_items_by_min_interval() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let min_interval = Duration::from_millis(20);
+        let start = Instant::now();
+        tokio::spawn(throttled_send(tx, vec![1, 2, 3, 4], min_interval));
+
+        let mut received = Vec::new();
+        while let Some(item) = rx.recv().await {
+            received.push(item);
+        }
+        assert_eq!(received, [1, 2, 3, 4]);
+        assert!(start.elapsed() >= min_interval * 3);
+    }
+
+    #[tokio::test]
+    async fn throttled_send_stops_when_receiver_drops() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let producer = tokio::spawn(throttled_send(
+            tx,
+            (1..=100).collect(),
+            Duration::from_millis(10),
+        ));
+
+        assert_eq!(rx.recv().await, Some(1));
+        drop(rx);
+        // Finishes long before the 100 items' 990ms schedule would
+        tokio::time::timeout(Duration::from_millis(200), producer)
+            .await
+            .expect("producer stops once the receiver is gone")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn watched_counter_publishes_every_change() {
+        let counter = WatchedCounterHandle::spawn();
+        let mut rx = counter.subscribe();
+        assert_eq!(*rx.borrow_and_update(), 0);
+
+        for expected in 1..=3 {
+            counter.increment().await;
+            rx.changed().await.unwrap();
+            assert_eq!(*rx.borrow_and_update(), expected);
+        }
+        counter.decrement().await;
+        rx.changed().await.unwrap();
+        assert_eq!(*rx.borrow_and_update(), 2);
+
+        assert_eq!(*counter.subscribe().borrow(), 2);
+        counter.reset().await;
+        rx.changed().await.unwrap();
+        assert_eq!(*rx.borrow_and_update(), 0);
+    }
+
+    #[tokio::test]
+    async fn into_stream_take_yields_exactly_the_requested_items() {
+        let (tx, rx) = mpsc::channel(10);
+        for i in 1..=5 {
+            tx.send(format!("event {}", i)).await.unwrap();
+        }
+
+        let taken: Vec<String> = into_stream(rx).take(3).collect().await;
+        assert_eq!(taken, ["event 1", "event 2", "event 3"]);
+        // take(3) dropped the stream, and with it the receiver
+        assert!(tx.is_closed());
+    }
+
+    #[tokio::test]
+    async fn into_stream_ends_when_senders_are_gone() {
+        let (tx, rx) = mpsc::channel(10);
+        tx.send("only".to_string()).await.unwrap();
+        drop(tx);
+        assert_eq!(into_stream(rx).collect::<Vec<_>>().await, ["only"]);
+    }
+}
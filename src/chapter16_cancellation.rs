@@ -1,7 +1,11 @@
 //! Chapter 16: Cancellation and Graceful Shutdown
 
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
-use tokio::sync::{broadcast, mpsc};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{Semaphore, broadcast, mpsc};
 use tokio::time::sleep;
 use tokio_util::sync::CancellationToken;
 
@@ -97,42 +101,127 @@ async fn demonstrate_broadcast_shutdown() {
     println!("\nAll workers stopped");
 }
 
+#[derive(Debug, Clone)]
+enum Signal {
+    Tick(u32),
+    Shutdown,
+}
+
+// Unlike worker_with_broadcast, a lagged receiver is not mistaken for shutdown
+async fn lag_tolerant_worker(id: u32, mut rx: broadcast::Receiver<Signal>) -> u64 {
+    println!("[Worker {}] Started", id);
+    let mut skipped = 0;
+
+    loop {
+        match rx.recv().await {
+            Ok(Signal::Tick(n)) => println!("[Worker {}] Tick {}", id, n),
+            Ok(Signal::Shutdown) => {
+                println!("[Worker {}] Shutdown signal received", id);
+                break;
+            }
+            Err(RecvError::Lagged(n)) => {
+                println!("[Worker {}] Lagged behind, skipped {} messages", id, n);
+                skipped += n;
+            }
+            Err(RecvError::Closed) => {
+                println!("[Worker {}] Channel closed", id);
+                break;
+            }
+        }
+    }
+
+    println!("[Worker {}] Stopped", id);
+    skipped
+}
+
+async fn demonstrate_lagging_receiver() {
+    println!("\n=== Recovering from a Lagging Receiver ===\n");
+
+    // Capacity 2: the receiver falls behind before it even starts reading
+    let (tx, _) = broadcast::channel::<Signal>(2);
+    let rx = tx.subscribe();
+
+    for n in 1..=10 {
+        let _ = tx.send(Signal::Tick(n));
+    }
+
+    let worker = tokio::spawn(lag_tolerant_worker(1, rx));
+
+    sleep(Duration::from_millis(100)).await;
+    println!("\n--- Broadcasting shutdown ---\n");
+    let _ = tx.send(Signal::Shutdown);
+
+    let skipped = worker.await.unwrap();
+    println!("\nWorker skipped {} messages but kept running", skipped);
+}
+
 struct GracefulShutdown {
     notify: broadcast::Sender<()>,
-    complete_tx: mpsc::Sender<()>,
-    complete_rx: mpsc::Receiver<()>,
+    complete_tx: mpsc::UnboundedSender<u32>,
+    complete_rx: mpsc::UnboundedReceiver<u32>,
+    pending: HashSet<u32>,
+}
+
+// Reports a worker's id when dropped, so completion is signaled even on early return
+struct DoneSignal {
+    id: u32,
+    tx: mpsc::UnboundedSender<u32>,
+}
+
+impl Drop for DoneSignal {
+    fn drop(&mut self) {
+        let _ = self.tx.send(self.id);
+    }
 }
 
 impl GracefulShutdown {
     fn new() -> Self {
         let (notify, _) = broadcast::channel(1);
-        let (complete_tx, complete_rx) = mpsc::channel(1);
+        let (complete_tx, complete_rx) = mpsc::unbounded_channel();
         Self {
             notify,
             complete_tx,
             complete_rx,
+            pending: HashSet::new(),
         }
     }
 
-    fn subscribe(&self) -> (broadcast::Receiver<()>, mpsc::Sender<()>) {
-        (self.notify.subscribe(), self.complete_tx.clone())
+    fn subscribe(&mut self, id: u32) -> (broadcast::Receiver<()>, DoneSignal) {
+        self.pending.insert(id);
+        let done = DoneSignal {
+            id,
+            tx: self.complete_tx.clone(),
+        };
+        (self.notify.subscribe(), done)
     }
 
     fn trigger(&self) {
         let _ = self.notify.send(());
     }
 
-    async fn wait_for_completion(&mut self, timeout: Duration) {
-        drop(self.complete_tx.clone()); // Drop our copy
+    // Returns the ids of workers that had not finished when the timeout expired
+    async fn wait_for_completion(&mut self, timeout: Duration) -> HashSet<u32> {
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        while !self.pending.is_empty() {
+            match tokio::time::timeout_at(deadline, self.complete_rx.recv()).await {
+                Ok(Some(id)) => {
+                    self.pending.remove(&id);
+                }
+                Ok(None) | Err(_) => break,
+            }
+        }
 
-        match tokio::time::timeout(timeout, self.complete_rx.recv()).await {
-            Ok(_) => println!("All tasks completed gracefully"),
-            Err(_) => println!("Timeout waiting for tasks"),
+        if self.pending.is_empty() {
+            println!("All tasks completed gracefully");
+        } else {
+            println!("Timeout waiting for tasks: {:?}", self.pending);
         }
+        std::mem::take(&mut self.pending)
     }
 }
 
-async fn graceful_worker(id: u32, mut shutdown: broadcast::Receiver<()>, _done: mpsc::Sender<()>) {
+async fn graceful_worker(id: u32, mut shutdown: broadcast::Receiver<()>, _done: DoneSignal) {
     println!("[Worker {}] Started", id);
 
     loop {
@@ -159,7 +248,7 @@ async fn demonstrate_graceful_shutdown() {
     let mut shutdown = GracefulShutdown::new();
 
     for i in 1..=3 {
-        let (shutdown_rx, done_tx) = shutdown.subscribe();
+        let (shutdown_rx, done_tx) = shutdown.subscribe(i);
         tokio::spawn(async move {
             graceful_worker(i, shutdown_rx, done_tx).await;
         });
@@ -173,11 +262,419 @@ async fn demonstrate_graceful_shutdown() {
     shutdown.wait_for_completion(Duration::from_secs(5)).await;
 }
 
+async fn demonstrate_stuck_worker_report() {
+    println!("\n=== Identifying Stuck Workers ===\n");
+
+    let mut shutdown = GracefulShutdown::new();
+
+    for i in 1..=3 {
+        let (shutdown_rx, done_tx) = shutdown.subscribe(i);
+        tokio::spawn(async move {
+            graceful_worker(i, shutdown_rx, done_tx).await;
+        });
+    }
+
+    // This worker never checks for shutdown
+    let (_ignored, done_tx) = shutdown.subscribe(99);
+    tokio::spawn(async move {
+        let _done = done_tx;
+        println!("[Worker 99] Started, ignoring shutdown");
+        sleep(Duration::from_secs(10)).await;
+    });
+
+    sleep(Duration::from_millis(200)).await;
+
+    println!("\n--- Initiating graceful shutdown ---\n");
+    shutdown.trigger();
+
+    let timed_out = shutdown
+        .wait_for_completion(Duration::from_millis(500))
+        .await;
+    println!("Workers that timed out: {:?}", timed_out);
+    assert_eq!(timed_out, HashSet::from([99]));
+}
+
+// Cancels the token when `signal` resolves. The signal is injectable because
+// tests can't easily deliver a real SIGINT; any future (e.g. a oneshot) will do.
+// Also returns if the token is cancelled some other way, so the task never leaks.
+async fn cancel_on_signal<S: Future<Output = ()>>(signal: S, token: CancellationToken) {
+    tokio::select! {
+        _ = signal => {
+            println!("[Signal] Received, cancelling");
+            token.cancel();
+        }
+        _ = token.cancelled() => {}
+    }
+}
+
+#[cfg(feature = "signal")]
+async fn wait_for_ctrl_c_then_cancel(token: CancellationToken) {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            // Without a handler there is nothing to wait for; never fire
+            println!("[Signal] Could not listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    cancel_on_signal(ctrl_c, token).await;
+}
+
+async fn demonstrate_signal_cancellation() {
+    println!("\n=== Cancelling on a Signal ===\n");
+
+    let token = CancellationToken::new();
+    let (simulated_tx, simulated_rx) = tokio::sync::oneshot::channel::<()>();
+
+    let mut handles = vec![];
+    for i in 1..=2 {
+        handles.push(tokio::spawn(worker_with_token(i, token.clone())));
+    }
+
+    #[cfg(feature = "signal")]
+    handles.push(tokio::spawn(wait_for_ctrl_c_then_cancel(token.clone())));
+    handles.push(tokio::spawn(cancel_on_signal(
+        async {
+            let _ = simulated_rx.await;
+        },
+        token.clone(),
+    )));
+
+    sleep(Duration::from_millis(300)).await;
+    println!("\n--- Simulating Ctrl-C ---\n");
+    let _ = simulated_tx.send(());
+
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    println!("\nToken cancelled: {}", token.is_cancelled());
+}
+
+#[derive(Debug)]
+enum RunOutcome<T> {
+    Completed(T),
+    TimedOut,
+    Cancelled,
+}
+
+// Run until the future finishes, the deadline passes, or the token fires
+async fn run_guarded<F: Future>(
+    fut: F,
+    deadline: Duration,
+    token: CancellationToken,
+) -> RunOutcome<F::Output> {
+    tokio::select! {
+        // Checked in order, so cancellation wins if several are ready at once
+        biased;
+        _ = token.cancelled() => RunOutcome::Cancelled,
+        _ = sleep(deadline) => RunOutcome::TimedOut,
+        output = fut => RunOutcome::Completed(output),
+    }
+}
+
+async fn demonstrate_run_guarded() {
+    println!("\n=== Timeout-or-Cancel Guard ===\n");
+
+    let job = |ms: u64| async move {
+        sleep(Duration::from_millis(ms)).await;
+        format!("finished after {}ms", ms)
+    };
+
+    let outcome = run_guarded(
+        job(50),
+        Duration::from_millis(200),
+        CancellationToken::new(),
+    )
+    .await;
+    println!("Fast job: {:?}", outcome);
+    assert!(matches!(outcome, RunOutcome::Completed(_)));
+
+    let outcome = run_guarded(
+        job(500),
+        Duration::from_millis(100),
+        CancellationToken::new(),
+    )
+    .await;
+    println!("Slow job: {:?}", outcome);
+    assert!(matches!(outcome, RunOutcome::TimedOut));
+
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+    tokio::spawn(async move {
+        sleep(Duration::from_millis(50)).await;
+        canceller.cancel();
+    });
+    let outcome = run_guarded(job(500), Duration::from_secs(5), token).await;
+    println!("Cancelled job: {:?}", outcome);
+    assert!(matches!(outcome, RunOutcome::Cancelled));
+}
+
+type DownloadResult = (String, Result<usize, String>);
+
+// Deterministic per URL, so reruns of the demo behave the same. FNV-1a rather
+// than DefaultHasher, whose output may change between Rust releases.
+fn simulated_response(url: &str) -> (Duration, Result<usize, String>) {
+    let roll = url.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    let delay = Duration::from_millis(20 + roll % 60);
+    if roll % 5 == 0 {
+        (delay, Err("HTTP 503".to_string()))
+    } else {
+        (delay, Ok(1024 + (roll % 64 * 1024) as usize))
+    }
+}
+
+// Capstone: spawn one task per URL, gate them with a semaphore, and let the
+// token abort both queued and in-flight downloads. Results keep input order.
+async fn download_all(
+    urls: Vec<String>,
+    max_concurrent: usize,
+    token: CancellationToken,
+) -> Vec<DownloadResult> {
+    download_all_with_peak(urls, max_concurrent, token).await.0
+}
+
+async fn download_all_with_peak(
+    urls: Vec<String>,
+    max_concurrent: usize,
+    token: CancellationToken,
+) -> (Vec<DownloadResult>, usize) {
+    // Zero permits would leave every download queued until cancelled
+    assert!(max_concurrent > 0, "max_concurrent must be non-zero");
+    let permits = Arc::new(Semaphore::new(max_concurrent));
+    let active = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+
+    let handles: Vec<_> = urls
+        .into_iter()
+        .map(|url| {
+            let permits = Arc::clone(&permits);
+            let active = Arc::clone(&active);
+            let peak = Arc::clone(&peak);
+            let token = token.clone();
+            tokio::spawn(async move {
+                let _permit = tokio::select! {
+                    // A token cancelled up front must win over a free permit
+                    biased;
+                    _ = token.cancelled() => return (url, Err("cancelled before start".to_string())),
+                    permit = permits.acquire_owned() => permit.expect("semaphore closed"),
+                };
+
+                let now_active = active.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now_active, Ordering::SeqCst);
+
+                let (delay, response) = simulated_response(&url);
+                let result = tokio::select! {
+                    _ = token.cancelled() => Err("cancelled in flight".to_string()),
+                    _ = sleep(delay) => response,
+                };
+
+                active.fetch_sub(1, Ordering::SeqCst);
+                (url, result)
+            })
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        results.push(handle.await.expect("download task panicked"));
+    }
+    (results, peak.load(Ordering::SeqCst))
+}
+
+async fn demonstrate_download_all() {
+    println!("\n=== Bounded Downloads ===\n");
+
+    let urls: Vec<String> = (1..=10)
+        .map(|i| format!("https://example.com/file{}.bin", i))
+        .collect();
+
+    let (results, peak) = download_all_with_peak(urls.clone(), 3, CancellationToken::new()).await;
+    for (url, result) in &results {
+        match result {
+            Ok(bytes) => println!("  {} -> {} bytes", url, bytes),
+            Err(e) => println!("  {} -> error: {}", url, e),
+        }
+    }
+    println!("Peak concurrency: {} (limit 3)", peak);
+    assert!(peak <= 3);
+
+    println!("\n--- Cancelling midway ---\n");
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+    tokio::spawn(async move {
+        sleep(Duration::from_millis(50)).await;
+        canceller.cancel();
+    });
+    let results = download_all(urls, 2, token).await;
+    let cancelled = results
+        .iter()
+        .filter(|(_, r)| matches!(r, Err(e) if e.starts_with("cancelled")))
+        .count();
+    let not_started = results
+        .iter()
+        .filter(|(_, r)| matches!(r, Err(e) if e == "cancelled before start"))
+        .count();
+    println!(
+        "Finished: {}, cancelled: {} ({} never started)",
+        results.len() - cancelled,
+        cancelled,
+        not_started
+    );
+    assert!(not_started > 0);
+}
+
 #[tokio::main]
 async fn main() {
     demonstrate_cancellation_token().await;
     demonstrate_broadcast_shutdown().await;
+    demonstrate_lagging_receiver().await;
     demonstrate_graceful_shutdown().await;
+    demonstrate_stuck_worker_report().await;
+    demonstrate_run_guarded().await;
+    demonstrate_signal_cancellation().await;
+    demonstrate_download_all().await;
 
     println!("\n=== All shutdown demos completed ===");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn download_all_keeps_order_and_limit() {
+        let urls: Vec<String> = (1..=6)
+            .map(|i| format!("https://example.com/{}", i))
+            .collect();
+        let (results, peak) =
+            download_all_with_peak(urls.clone(), 2, CancellationToken::new()).await;
+        let order: Vec<_> = results.into_iter().map(|(url, _)| url).collect();
+        assert_eq!(order, urls);
+        assert!((1..=2).contains(&peak));
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "max_concurrent must be non-zero")]
+    async fn download_all_rejects_zero_limit() {
+        let urls = vec!["https://example.com/1".to_string()];
+        download_all(urls, 0, CancellationToken::new()).await;
+    }
+
+    #[tokio::test]
+    async fn lag_tolerant_worker_survives_lag_until_shutdown() {
+        let (tx, rx) = broadcast::channel(2);
+        for n in 1..=10 {
+            tx.send(Signal::Tick(n)).unwrap();
+        }
+
+        let worker = tokio::spawn(lag_tolerant_worker(1, rx));
+        sleep(Duration::from_millis(50)).await;
+        assert!(!worker.is_finished(), "lagging must not stop the worker");
+
+        tx.send(Signal::Shutdown).unwrap();
+        let skipped = worker.await.unwrap();
+        assert_eq!(skipped, 8);
+    }
+
+    #[tokio::test]
+    async fn wait_for_completion_reports_only_stuck_workers() {
+        let mut shutdown = GracefulShutdown::new();
+        for i in 1..=2 {
+            let (shutdown_rx, done) = shutdown.subscribe(i);
+            tokio::spawn(graceful_worker(i, shutdown_rx, done));
+        }
+        let (_ignored, done) = shutdown.subscribe(99);
+        tokio::spawn(async move {
+            let _done = done;
+            sleep(Duration::from_secs(10)).await;
+        });
+
+        shutdown.trigger();
+        let timed_out = shutdown
+            .wait_for_completion(Duration::from_millis(300))
+            .await;
+        assert_eq!(timed_out, HashSet::from([99]));
+    }
+
+    async fn job(ms: u64) -> u64 {
+        sleep(Duration::from_millis(ms)).await;
+        ms
+    }
+
+    #[tokio::test]
+    async fn run_guarded_completes_before_deadline() {
+        let outcome = run_guarded(job(10), Duration::from_secs(1), CancellationToken::new()).await;
+        assert!(matches!(outcome, RunOutcome::Completed(10)));
+    }
+
+    #[tokio::test]
+    async fn run_guarded_times_out_slow_work() {
+        let outcome = run_guarded(
+            job(1_000),
+            Duration::from_millis(20),
+            CancellationToken::new(),
+        )
+        .await;
+        assert!(matches!(outcome, RunOutcome::TimedOut));
+    }
+
+    #[tokio::test]
+    async fn run_guarded_stops_on_cancel() {
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(20)).await;
+            canceller.cancel();
+        });
+        let outcome = run_guarded(job(1_000), Duration::from_secs(5), token).await;
+        assert!(matches!(outcome, RunOutcome::Cancelled));
+    }
+
+    #[tokio::test]
+    async fn cancel_on_signal_cancels_when_signal_fires() {
+        let token = CancellationToken::new();
+        let (signal_tx, signal_rx) = tokio::sync::oneshot::channel::<()>();
+        let listener = tokio::spawn(cancel_on_signal(
+            async {
+                let _ = signal_rx.await;
+            },
+            token.clone(),
+        ));
+
+        assert!(!token.is_cancelled());
+        signal_tx.send(()).unwrap();
+        listener.await.unwrap();
+        assert!(token.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn cancel_on_signal_returns_when_token_cancelled_elsewhere() {
+        let token = CancellationToken::new();
+        let listener = tokio::spawn(cancel_on_signal(std::future::pending(), token.clone()));
+        token.cancel();
+        listener.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn download_all_with_cancelled_token_starts_nothing() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let urls: Vec<String> = (1..=5)
+            .map(|i| format!("https://example.com/{}", i))
+            .collect();
+
+        let results = download_all(urls.clone(), 2, token).await;
+        assert_eq!(results.len(), urls.len());
+        for (url, (got, result)) in urls.iter().zip(&results) {
+            assert_eq!(got, url);
+            assert_eq!(result, &Err("cancelled before start".to_string()));
+        }
+    }
+
+    #[test]
+    fn simulated_response_is_stable_per_url() {
+        let url = "https://example.com/file1.bin";
+        assert_eq!(simulated_response(url), simulated_response(url));
+    }
+}
@@ -1,7 +1,10 @@
 //! Chapter 17: Concurrent Data Structures - Atomics
 
-use std::sync::atomic::{AtomicU64, AtomicBool, Ordering};
-use std::sync::Arc;
+use std::cell::UnsafeCell;
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicU64, AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
@@ -177,10 +180,392 @@ fn demonstrate_statistics_counter() {
     println!("Average: {:.2}", stats.average());
 }
 
+// Treiber stack: a lock-free linked list where every update is a CAS on `head`.
+//
+// Popped nodes are deliberately leaked. A thread that loaded an old `head` may
+// still read its `next` pointer, so freeing nodes would need hazard pointers or
+// epochs (see crossbeam-epoch). Leaking also rules out ABA: an address is never
+// reused, so a successful CAS always means the head really is unchanged.
+struct Node<T> {
+    value: ManuallyDrop<T>,
+    next: *mut Node<T>,
+}
+
+struct TreiberStack<T> {
+    head: AtomicPtr<Node<T>>,
+}
+
+// Values move between threads, but nodes are only reachable through the atomic head
+unsafe impl<T: Send> Send for TreiberStack<T> {}
+unsafe impl<T: Send> Sync for TreiberStack<T> {}
+
+impl<T> TreiberStack<T> {
+    fn new() -> Self {
+        Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    fn push(&self, value: T) {
+        let node = Box::into_raw(Box::new(Node {
+            value: ManuallyDrop::new(value),
+            next: ptr::null_mut(),
+        }));
+
+        loop {
+            let head = self.head.load(Ordering::Relaxed);
+            // SAFETY: node is not yet shared, so we have exclusive access
+            unsafe { (*node).next = head };
+            if self
+                .head
+                .compare_exchange_weak(head, node, Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                return;
+            }
+        }
+    }
+
+    fn pop(&self) -> Option<T> {
+        loop {
+            let head = self.head.load(Ordering::Acquire);
+            if head.is_null() {
+                return None;
+            }
+            // SAFETY: nodes are never freed while the stack is shared
+            let next = unsafe { (*head).next };
+            if self
+                .head
+                .compare_exchange_weak(head, next, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+            {
+                // SAFETY: winning the CAS makes us the only thread to take this value
+                return Some(unsafe { ManuallyDrop::take(&mut (*head).value) });
+            }
+        }
+    }
+}
+
+impl<T> Drop for TreiberStack<T> {
+    fn drop(&mut self) {
+        // &mut self means no other thread can observe the list any more
+        let mut current = *self.head.get_mut();
+        while !current.is_null() {
+            let mut node = unsafe { Box::from_raw(current) };
+            current = node.next;
+            unsafe { ManuallyDrop::drop(&mut node.value) };
+        }
+    }
+}
+
+fn demonstrate_treiber_stack() {
+    println!("\n=== Lock-Free Treiber Stack ===\n");
+
+    const PRODUCERS: u64 = 4;
+    const PER_PRODUCER: u64 = 1000;
+    const TOTAL: usize = (PRODUCERS * PER_PRODUCER) as usize;
+
+    let stack = Arc::new(TreiberStack::new());
+    let popped_count = Arc::new(AtomicUsize::new(0));
+    let popped = Arc::new(Mutex::new(Vec::with_capacity(TOTAL)));
+    let mut handles = vec![];
+
+    for p in 0..PRODUCERS {
+        let stack = Arc::clone(&stack);
+        handles.push(thread::spawn(move || {
+            for i in 0..PER_PRODUCER {
+                stack.push(p * PER_PRODUCER + i);
+            }
+        }));
+    }
+
+    for _ in 0..4 {
+        let stack = Arc::clone(&stack);
+        let popped_count = Arc::clone(&popped_count);
+        let popped = Arc::clone(&popped);
+        handles.push(thread::spawn(move || {
+            let mut local = Vec::new();
+            while popped_count.load(Ordering::Relaxed) < TOTAL {
+                if let Some(value) = stack.pop() {
+                    popped_count.fetch_add(1, Ordering::Relaxed);
+                    local.push(value);
+                }
+            }
+            popped.lock().unwrap().extend(local);
+        }));
+    }
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let mut popped = popped.lock().unwrap();
+    popped.sort();
+    let expected: Vec<u64> = (0..PRODUCERS * PER_PRODUCER).collect();
+    println!("Pushed {} values, popped {}", TOTAL, popped.len());
+    assert_eq!(*popped, expected, "every value popped exactly once");
+    println!("Every value popped exactly once");
+}
+
+// SPSC ring buffer. `head` and `tail` are free-running counters; masking with
+// `capacity - 1` maps them to slots, so capacity must be a power of two.
+// Only the consumer writes `head` and only the producer writes `tail`.
+struct RingBuffer<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    mask: usize,
+    head: AtomicUsize, // Next slot to pop
+    tail: AtomicUsize, // Next slot to push
+}
+
+// Each slot is touched by one side at a time, handed over through head/tail
+unsafe impl<T: Send> Send for RingBuffer<T> {}
+unsafe impl<T: Send> Sync for RingBuffer<T> {}
+
+impl<T> RingBuffer<T> {
+    fn new(capacity: usize) -> Self {
+        assert!(capacity.is_power_of_two(), "capacity must be a power of two");
+        Self {
+            slots: (0..capacity)
+                .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+                .collect(),
+            mask: capacity - 1,
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    // SAFETY: at most one thread may call try_push at any time
+    unsafe fn try_push(&self, value: T) -> Result<(), T> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        // Acquire pairs with the consumer's Release so the slot is really free
+        let head = self.head.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) > self.mask {
+            return Err(value); // Full
+        }
+        unsafe { (*self.slots[tail & self.mask].get()).write(value) };
+        // Release publishes the write above to the consumer
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    // SAFETY: at most one thread may call try_pop at any time
+    unsafe fn try_pop(&self) -> Option<T> {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+        if head == tail {
+            return None; // Empty
+        }
+        let value = unsafe { (*self.slots[head & self.mask].get()).assume_init_read() };
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+}
+
+impl<T> Drop for RingBuffer<T> {
+    fn drop(&mut self) {
+        while unsafe { self.try_pop() }.is_some() {}
+    }
+}
+
+// Non-Clone handles are what make the single-producer/single-consumer rule hold
+struct Producer<T>(Arc<RingBuffer<T>>);
+struct Consumer<T>(Arc<RingBuffer<T>>);
+
+impl<T> Producer<T> {
+    fn try_push(&mut self, value: T) -> Result<(), T> {
+        unsafe { self.0.try_push(value) }
+    }
+}
+
+impl<T> Consumer<T> {
+    fn try_pop(&mut self) -> Option<T> {
+        unsafe { self.0.try_pop() }
+    }
+}
+
+fn ring_buffer<T>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    let buffer = Arc::new(RingBuffer::new(capacity));
+    (Producer(Arc::clone(&buffer)), Consumer(buffer))
+}
+
+fn demonstrate_ring_buffer() {
+    println!("\n=== Lock-Free SPSC Ring Buffer ===\n");
+
+    const ITEMS: u64 = 100_000;
+    let (mut producer, mut consumer) = ring_buffer::<u64>(64);
+
+    let writer = thread::spawn(move || {
+        let mut full_spins = 0;
+        for i in 0..ITEMS {
+            let mut value = i;
+            while let Err(v) = producer.try_push(value) {
+                value = v;
+                full_spins += 1;
+                std::hint::spin_loop();
+            }
+        }
+        full_spins
+    });
+
+    let reader = thread::spawn(move || {
+        let mut expected = 0;
+        let mut in_order = true;
+        while expected < ITEMS {
+            if let Some(value) = consumer.try_pop() {
+                in_order &= value == expected;
+                expected += 1;
+            } else {
+                std::hint::spin_loop();
+            }
+        }
+        (expected, in_order)
+    });
+
+    let full_spins = writer.join().unwrap();
+    let (received, in_order) = reader.join().unwrap();
+    println!("Sent {} items, received {}", ITEMS, received);
+    assert_eq!(received, ITEMS, "every item received");
+    assert!(in_order, "items received in the order they were sent");
+    println!("Received in order");
+    println!("Producer found the buffer full {} times", full_spins);
+}
+
 fn main() {
     demonstrate_atomic_counter();
     demonstrate_atomic_flag();
     demonstrate_compare_and_swap();
     demonstrate_lock_free_max();
     demonstrate_statistics_counter();
+    demonstrate_treiber_stack();
+    demonstrate_ring_buffer();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn treiber_stack_is_lifo() {
+        let stack = TreiberStack::new();
+        for i in 0..3 {
+            stack.push(i);
+        }
+        assert_eq!(stack.pop(), Some(2));
+        assert_eq!(stack.pop(), Some(1));
+        assert_eq!(stack.pop(), Some(0));
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn treiber_stack_pops_each_value_once_under_contention() {
+        const PER_THREAD: u64 = 2_000;
+        let stack = Arc::new(TreiberStack::new());
+
+        let pushers: Vec<_> = (0..4u64)
+            .map(|t| {
+                let stack = Arc::clone(&stack);
+                thread::spawn(move || {
+                    for i in 0..PER_THREAD {
+                        stack.push(t * PER_THREAD + i);
+                    }
+                })
+            })
+            .collect();
+        let poppers: Vec<_> = (0..4)
+            .map(|_| {
+                let stack = Arc::clone(&stack);
+                thread::spawn(move || {
+                    let mut popped = Vec::new();
+                    for _ in 0..PER_THREAD * 2 {
+                        if let Some(value) = stack.pop() {
+                            popped.push(value);
+                        }
+                    }
+                    popped
+                })
+            })
+            .collect();
+
+        for pusher in pushers {
+            pusher.join().unwrap();
+        }
+        let mut popped: Vec<u64> = poppers
+            .into_iter()
+            .flat_map(|popper| popper.join().unwrap())
+            .collect();
+        while let Some(value) = stack.pop() {
+            popped.push(value);
+        }
+        popped.sort_unstable();
+        assert_eq!(popped, (0..4 * PER_THREAD).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn treiber_stack_drops_remaining_values() {
+        let value = Arc::new(());
+        let stack = TreiberStack::new();
+        stack.push(Arc::clone(&value));
+        stack.push(Arc::clone(&value));
+        drop(stack);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn ring_buffer_is_fifo_and_bounded() {
+        let (mut producer, mut consumer) = ring_buffer(4);
+        assert_eq!(consumer.try_pop(), None);
+        for i in 0..4 {
+            assert_eq!(producer.try_push(i), Ok(()));
+        }
+        assert_eq!(producer.try_push(4), Err(4));
+        assert_eq!(consumer.try_pop(), Some(0));
+        assert_eq!(producer.try_push(4), Ok(()));
+        for i in 1..5 {
+            assert_eq!(consumer.try_pop(), Some(i));
+        }
+        assert_eq!(consumer.try_pop(), None);
+    }
+
+    #[test]
+    fn ring_buffer_delivers_in_order_across_threads() {
+        const ITEMS: u64 = 10_000;
+        let (mut producer, mut consumer) = ring_buffer(8);
+
+        let writer = thread::spawn(move || {
+            for i in 0..ITEMS {
+                let mut value = i;
+                while let Err(v) = producer.try_push(value) {
+                    value = v;
+                    thread::yield_now();
+                }
+            }
+        });
+
+        let mut received = Vec::new();
+        while received.len() < ITEMS as usize {
+            match consumer.try_pop() {
+                Some(value) => received.push(value),
+                None => thread::yield_now(),
+            }
+        }
+        writer.join().unwrap();
+        assert_eq!(received, (0..ITEMS).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn ring_buffer_drops_remaining_values() {
+        let value = Arc::new(());
+        let (mut producer, consumer) = ring_buffer(4);
+        producer.try_push(Arc::clone(&value)).unwrap();
+        producer.try_push(Arc::clone(&value)).unwrap();
+        drop(producer);
+        drop(consumer);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity must be a power of two")]
+    fn ring_buffer_rejects_non_power_of_two_capacity() {
+        let _ = ring_buffer::<u8>(6);
+    }
 }
@@ -1,7 +1,9 @@
 //! Chapter 17: Concurrent Data Structures - Work Stealing
 
 use crossbeam::deque::{Injector, Stealer, Worker};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::hash::Hash;
+use std::iter;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
@@ -96,6 +98,88 @@ fn demonstrate_work_stealing() {
     println!("\nTotal tasks processed: {}", total);
 }
 
+// Local queue first, then a batch from the injector, then other workers.
+// Steal::Retry means we lost a race, not that the queues are empty.
+fn find_task<T>(local: &Worker<T>, global: &Injector<T>, stealers: &[Stealer<T>]) -> Option<T> {
+    local.pop().or_else(|| {
+        iter::repeat_with(|| {
+            global
+                .steal_batch_and_pop(local)
+                .or_else(|| stealers.iter().map(|s| s.steal()).collect())
+        })
+        .find(|s| !s.is_retry())
+        .and_then(|s| s.success())
+    })
+}
+
+// Each task carries its input index, so results can be put back in order.
+// Scoped threads let `f` and the queues be borrowed instead of Arc-wrapped.
+fn parallel_map<T, R, F>(items: Vec<T>, workers: usize, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let len = items.len();
+    let injector = Injector::new();
+    for task in items.into_iter().enumerate() {
+        injector.push(task);
+    }
+
+    let locals: Vec<Worker<(usize, T)>> = (0..workers.max(1)).map(|_| Worker::new_fifo()).collect();
+    let stealers: Vec<Stealer<(usize, T)>> = locals.iter().map(|w| w.stealer()).collect();
+
+    let mut slots: Vec<Option<R>> = iter::repeat_with(|| None).take(len).collect();
+    thread::scope(|s| {
+        let handles: Vec<_> = locals
+            .into_iter()
+            .map(|local| {
+                let (injector, stealers, f) = (&injector, &stealers, &f);
+                s.spawn(move || {
+                    // All work is queued up front, so finding nothing means we're done
+                    let mut results = Vec::new();
+                    while let Some((index, item)) = find_task(&local, injector, stealers) {
+                        results.push((index, f(item)));
+                    }
+                    results
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            for (index, result) in handle.join().unwrap() {
+                slots[index] = Some(result);
+            }
+        }
+    });
+
+    slots.into_iter().map(|r| r.expect("every task runs once")).collect()
+}
+
+fn demonstrate_parallel_map() {
+    println!("\n=== Work-Stealing parallel_map ===\n");
+
+    let input: Vec<u64> = (0..100_000).collect();
+    let expected: Vec<u64> = input.iter().map(|x| x * 2).collect();
+
+    for workers in [1, 4, 8] {
+        let output = parallel_map(input.clone(), workers, |x| x * 2);
+        assert_eq!(output, expected, "{} workers", workers);
+        println!(
+            "{} workers: {} results, ordered and equal to sequential map",
+            workers,
+            output.len()
+        );
+    }
+
+    // Uneven costs are where stealing pays off
+    let lengths = parallel_map(vec!["a", "bbb", "cc", "dddd"], 3, |s| {
+        thread::sleep(Duration::from_millis(s.len() as u64 * 5));
+        s.len()
+    });
+    println!("Lengths in input order: {:?}", lengths);
+}
+
 fn demonstrate_dashmap() {
     println!("\n=== DashMap (Concurrent HashMap) ===\n");
 
@@ -147,6 +231,201 @@ fn demonstrate_dashmap() {
     }
 }
 
+// The entry guard holds the shard lock, so the update and the read are one atomic step
+fn add_and_get(map: &dashmap::DashMap<String, u64>, key: &str, delta: u64) -> u64 {
+    let mut value = map.entry(key.to_string()).or_insert(0);
+    *value += delta;
+    *value
+}
+
+fn demonstrate_add_and_get() {
+    println!("\n=== DashMap Add-and-Get ===\n");
+
+    use dashmap::DashMap;
+
+    let map: Arc<DashMap<String, u64>> = Arc::new(DashMap::new());
+    let mut handles = vec![];
+
+    for i in 1..=4u64 {
+        let map = Arc::clone(&map);
+        handles.push(thread::spawn(move || {
+            (0..100)
+                .map(|_| (i, add_and_get(&map, "hits", i)))
+                .collect::<Vec<_>>()
+        }));
+    }
+
+    let mut readings = Vec::new();
+    for handle in handles {
+        readings.extend(handle.join().unwrap());
+    }
+
+    // Each reading is the value right after its own delta was applied,
+    // so sorting by reading must replay the running total exactly.
+    readings.sort_by_key(|&(_, after)| after);
+    let mut running = 0;
+    let consistent = readings.iter().all(|&(delta, after)| {
+        running += delta;
+        running == after
+    });
+
+    let total_added: u64 = readings.iter().map(|&(delta, _)| delta).sum();
+    println!("Total added: {}", total_added);
+    println!("Final value: {}", *map.get("hits").unwrap());
+    assert!(consistent, "returned values must replay the running total");
+    assert_eq!(*map.get("hits").unwrap(), total_added);
+    println!("Every returned value consistent");
+}
+
+// LRU cache on DashMap. Reads touch only their shard plus an atomic timestamp,
+// so there is no global lock on the hot path.
+//
+// Approximations: eviction scans for the oldest timestamp, which is O(n) and can
+// race with concurrent reads, so the victim is "one of the least recently used"
+// rather than exactly the oldest. Capacity is exact: inserts reserve a slot in
+// `len` before touching the map, so the map never holds more than `capacity`.
+struct ConcurrentLru<K, V> {
+    map: dashmap::DashMap<K, (V, AtomicU64)>,
+    capacity: usize,
+    clock: AtomicU64,
+    len: AtomicUsize, // Reserved slots, always >= map.len()
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> ConcurrentLru<K, V> {
+    fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must be non-zero");
+        Self {
+            map: dashmap::DashMap::new(),
+            capacity,
+            clock: AtomicU64::new(0),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn get(&self, key: &K) -> Option<V> {
+        self.map.get(key).map(|entry| {
+            entry.1.store(self.tick(), Ordering::Relaxed);
+            entry.0.clone()
+        })
+    }
+
+    fn insert(&self, key: K, value: V) {
+        if let Some(mut entry) = self.map.get_mut(&key) {
+            entry.0 = value;
+            entry.1.store(self.tick(), Ordering::Relaxed);
+            return;
+        }
+
+        self.reserve_slot();
+        let stamp = AtomicU64::new(self.tick());
+        // Another thread inserted the same key meanwhile: we replaced it, so give the slot back
+        if self.map.insert(key, (value, stamp)).is_some() {
+            self.len.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+
+    fn reserve_slot(&self) {
+        loop {
+            let len = self.len.load(Ordering::Acquire);
+            if len < self.capacity {
+                if self
+                    .len
+                    .compare_exchange(len, len + 1, Ordering::AcqRel, Ordering::Acquire)
+                    .is_ok()
+                {
+                    return;
+                }
+            } else if !self.evict_one() {
+                // Slots are reserved but not inserted yet; wait for them to land
+                thread::yield_now();
+            }
+        }
+    }
+
+    fn evict_one(&self) -> bool {
+        // Clone the key so no shard guard is held while removing
+        let victim = self
+            .map
+            .iter()
+            .min_by_key(|entry| entry.value().1.load(Ordering::Relaxed))
+            .map(|entry| entry.key().clone());
+
+        match victim {
+            Some(key) if self.map.remove(&key).is_some() => {
+                self.len.fetch_sub(1, Ordering::AcqRel);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // The slot count, not map.len(): DashMap sums its shards one at a time, so
+    // under concurrent writes its len() is not a snapshot and can overcount
+    fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+}
+
+fn demonstrate_concurrent_lru() {
+    println!("\n=== DashMap-Based Concurrent LRU ===\n");
+
+    let lru = ConcurrentLru::new(3);
+    for key in ["a", "b", "c"] {
+        lru.insert(key, key.to_uppercase());
+    }
+    lru.get(&"a"); // "b" is now the least recently used
+    lru.insert("d", "D".to_string());
+    for key in ["a", "b", "c", "d"] {
+        println!("  {} -> {:?}", key, lru.get(&key));
+    }
+
+    const CAPACITY: usize = 8;
+    let lru = Arc::new(ConcurrentLru::new(CAPACITY));
+    let done = Arc::new(AtomicBool::new(false));
+
+    let monitor = {
+        let lru = Arc::clone(&lru);
+        let done = Arc::clone(&done);
+        thread::spawn(move || {
+            let mut max_seen = 0;
+            while !done.load(Ordering::Relaxed) {
+                max_seen = max_seen.max(lru.len());
+            }
+            max_seen
+        })
+    };
+
+    let workers: Vec<_> = (0..4u64)
+        .map(|t| {
+            let lru = Arc::clone(&lru);
+            thread::spawn(move || {
+                for i in 0..2_000u64 {
+                    let key = (i * 7 + t * 13) % 40;
+                    if lru.get(&key).is_none() {
+                        lru.insert(key, key * 10);
+                    }
+                }
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    done.store(true, Ordering::Relaxed);
+    let max_seen = monitor.join().unwrap();
+
+    println!("\nCapacity: {}", CAPACITY);
+    println!("Largest size observed while hammering: {}", max_seen);
+    println!("Final size: {} ({} entries in the map)", lru.len(), lru.map.len());
+    assert!(max_seen <= CAPACITY, "size never exceeds capacity");
+    println!("Never exceeded capacity");
+}
+
 fn demonstrate_crossbeam_channel() {
     println!("\n=== Crossbeam MPMC Channel ===\n");
 
@@ -188,10 +467,145 @@ fn demonstrate_crossbeam_channel() {
     }
 }
 
+fn consume_two<T: Send>(
+    mut a: crossbeam::channel::Receiver<T>,
+    mut b: crossbeam::channel::Receiver<T>,
+    mut on_item: impl FnMut(T),
+) {
+    use crossbeam::channel::{never, select};
+
+    let (mut a_open, mut b_open) = (true, true);
+
+    while a_open || b_open {
+        // select! picks randomly among ready operations, so neither side starves
+        select! {
+            recv(a) -> msg => match msg {
+                Ok(item) => on_item(item),
+                Err(_) => {
+                    a_open = false;
+                    a = never(); // A closed channel would always be ready
+                }
+            },
+            recv(b) -> msg => match msg {
+                Ok(item) => on_item(item),
+                Err(_) => {
+                    b_open = false;
+                    b = never();
+                }
+            },
+        }
+    }
+}
+
+fn demonstrate_select_consumer() {
+    println!("\n=== Crossbeam select! Over Two Channels ===\n");
+
+    use crossbeam::channel;
+
+    let (tx_a, rx_a) = channel::unbounded::<u64>();
+    let (tx_b, rx_b) = channel::bounded::<u64>(5);
+
+    let producer_a = thread::spawn(move || {
+        for i in 0..20 {
+            tx_a.send(i).unwrap();
+        }
+    });
+    let producer_b = thread::spawn(move || {
+        for i in 100..120 {
+            tx_b.send(i).unwrap();
+            thread::sleep(Duration::from_micros(200));
+        }
+    });
+
+    let mut received = Vec::new();
+    consume_two(rx_a, rx_b, |item| received.push(item));
+
+    producer_a.join().unwrap();
+    producer_b.join().unwrap();
+
+    received.sort();
+    let expected: Vec<u64> = (0..20).chain(100..120).collect();
+    println!("Received {} items", received.len());
+    assert_eq!(received, expected, "every item delivered exactly once");
+    println!("Every item delivered exactly once");
+}
+
 fn main() {
     demonstrate_work_stealing();
+    demonstrate_parallel_map();
     demonstrate_dashmap();
+    demonstrate_add_and_get();
+    demonstrate_concurrent_lru();
     demonstrate_crossbeam_channel();
+    demonstrate_select_consumer();
 
     println!("\n=== All concurrent data structure demos completed ===");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parallel_map_preserves_input_order() {
+        let input: Vec<u64> = (0..1_000).collect();
+        let expected: Vec<u64> = input.iter().map(|x| x * 2).collect();
+        for workers in [1, 3, 8] {
+            assert_eq!(parallel_map(input.clone(), workers, |x| x * 2), expected);
+        }
+        assert_eq!(parallel_map(Vec::<u64>::new(), 4, |x| x), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn lru_evicts_least_recently_used() {
+        let lru = ConcurrentLru::new(2);
+        lru.insert("a", 1);
+        lru.insert("b", 2);
+        assert_eq!(lru.get(&"a"), Some(1));
+        lru.insert("c", 3);
+
+        assert_eq!(lru.get(&"b"), None);
+        assert_eq!(lru.get(&"a"), Some(1));
+        assert_eq!(lru.get(&"c"), Some(3));
+        assert_eq!(lru.len(), 2);
+    }
+
+    #[test]
+    fn lru_update_does_not_take_a_new_slot() {
+        let lru = ConcurrentLru::new(2);
+        lru.insert("a", 1);
+        lru.insert("a", 2);
+        assert_eq!(lru.get(&"a"), Some(2));
+        assert_eq!(lru.len(), 1);
+    }
+
+    #[test]
+    fn lru_stays_within_capacity_under_contention() {
+        const CAPACITY: usize = 4;
+        let lru = Arc::new(ConcurrentLru::new(CAPACITY));
+
+        let workers: Vec<_> = (0..4u64)
+            .map(|t| {
+                let lru = Arc::clone(&lru);
+                thread::spawn(move || {
+                    for i in 0..500u64 {
+                        lru.insert((i * 7 + t * 13) % 20, i);
+                        assert!(lru.len() <= CAPACITY);
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        assert!(lru.len() <= CAPACITY);
+        assert_eq!(lru.len(), lru.map.len());
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity must be non-zero")]
+    fn lru_rejects_zero_capacity() {
+        ConcurrentLru::<u8, u8>::new(0);
+    }
+}